@@ -0,0 +1,492 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::render;
+use crate::{Post, PostId, PostRepository, State, Verdict};
+
+pub const USAGE: &str = "\
+Usage: blog [OPTION]... COMMAND [ARG]...
+Manage blog posts through their editorial workflow.
+
+Commands:
+  new                        create a draft and print its id
+  edit ID                    replace the text of a draft or rejected post
+  submit ID                  send a post for review
+  approve ID --by=NAME       approve a post under review, to be published
+  reject ID --by=NAME --reason=TEXT
+                             send a post under review back to its author
+  publish ID                 publish an approved or scheduled post now
+  publish ID --at=TIME       schedule an approved post for TIME, given as
+                             RFC 3339 (e.g. 2026-11-01T09:00:00Z)
+  publish --due              publish every scheduled post whose time has come
+  list                       list the posts, one per line: ID, state, title
+  show ID                    print a post with its reviews and revisions
+  help                       display this help and exit
+
+Options:
+      --store=FILE           keep posts in FILE (default: $BLOG_STORE, or
+                             posts.json)
+      --author=NAME          who is editing (default: $BLOG_AUTHOR, or $USER)
+      --text=TEXT            with new and edit, the text of the post; it is
+                             read from standard input otherwise
+      --append               with edit, add the text to the end of the post
+      --state=STATE          with list, only list posts in STATE: draft,
+                             pending-review, rejected, approved, scheduled,
+                             published or archived
+      --html                 with show, print the text rendered as HTML
+
+Exit status is 0 on success, 1 if the command failed, and 2 on a usage error.
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    New,
+    Edit(PostId),
+    Submit(PostId),
+    Approve(PostId),
+    Reject(PostId),
+    /// Publish a post now, or schedule it with `--at`.
+    Publish(PostId),
+    PublishDue,
+    List,
+    Show(PostId),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliError {
+    Help,
+    MissingCommand,
+    UnknownCommand(String),
+    UnknownOption(String),
+    MissingValue(String),
+    UnexpectedValue(String),
+    InvalidValue { option: String, value: String },
+    MissingArgument(&'static str),
+    UnexpectedArgument(String),
+    /// `command` needs `--option`.
+    Requires { command: &'static str, option: &'static str },
+    /// `--option` means nothing to `command`.
+    NotApplicable { option: String, command: &'static str },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help => write!(f, "help requested"),
+            CliError::MissingCommand => write!(f, "no command given"),
+            CliError::UnknownCommand(command) => write!(f, "unknown command '{}'", command),
+            CliError::UnknownOption(option) => write!(f, "unrecognized option '{}'", option),
+            CliError::MissingValue(option) => write!(f, "option '{}' requires a value", option),
+            CliError::UnexpectedValue(option) => write!(f, "option '{}' doesn't take a value", option),
+            CliError::InvalidValue { option, value } => write!(f, "invalid value '{}' for '{}'", value, option),
+            CliError::MissingArgument(what) => write!(f, "missing {}", what),
+            CliError::UnexpectedArgument(arg) => write!(f, "unexpected argument '{}'", arg),
+            CliError::Requires { command, option } => write!(f, "{} requires --{}", command, option),
+            CliError::NotApplicable { option, command } => write!(f, "--{} can't be used with {}", option, command),
+        }
+    }
+}
+
+impl Error for CliError {}
+
+/// The options every command accepts, as (name, takes_value).
+const OPTIONS: &[(&str, bool)] = &[
+    ("store", true),
+    ("author", true),
+    ("text", true),
+    ("append", false),
+    ("by", true),
+    ("reason", true),
+    ("at", true),
+    ("due", false),
+    ("state", true),
+    ("html", false),
+    ("help", false),
+];
+
+/// The options only some commands make sense with.
+const COMMAND_OPTIONS: &[(&str, &[&str])] = &[
+    ("text", &["new", "edit"]),
+    ("append", &["edit"]),
+    ("by", &["approve", "reject"]),
+    ("reason", &["reject"]),
+    ("at", &["publish"]),
+    ("due", &["publish"]),
+    ("state", &["list"]),
+    ("html", &["show"]),
+];
+
+/// A parsed command line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cli {
+    pub command: Option<Command>,
+    pub store: Option<PathBuf>,
+    pub author: Option<String>,
+    pub text: Option<String>,
+    pub append: bool,
+    pub by: Option<String>,
+    pub reason: Option<String>,
+    pub at: Option<SystemTime>,
+    pub state: Option<State>,
+    pub html: bool,
+}
+
+impl Cli {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_iter<I>(mut args: I) -> Result<Cli, CliError>
+    where
+        I: Iterator<Item = String>,
+    {
+        args.next();
+
+        let mut cli = Cli::default();
+        let mut positional = Vec::new();
+        let mut given = Vec::new();
+        let mut due = false;
+        while let Some(arg) = args.next() {
+            if arg == "-h" {
+                return Err(CliError::Help);
+            }
+            let option = match arg.strip_prefix("--") {
+                Some(option) => option,
+                None => {
+                    positional.push(arg);
+                    continue;
+                }
+            };
+            let (name, inline) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (option, None),
+            };
+            let takes_value = match OPTIONS.iter().find(|(n, _)| *n == name) {
+                Some(&(_, takes_value)) => takes_value,
+                None => return Err(CliError::UnknownOption(arg)),
+            };
+            let value = match (takes_value, inline) {
+                (true, Some(value)) => value,
+                (true, None) => args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?,
+                (false, Some(_)) => return Err(CliError::UnexpectedValue(arg)),
+                (false, None) => String::new(),
+            };
+            let invalid = || CliError::InvalidValue { option: format!("--{}", name), value: value.clone() };
+            match name {
+                "help" => return Err(CliError::Help),
+                "store" => cli.store = Some(PathBuf::from(&value)),
+                "author" => cli.author = Some(value.clone()),
+                "text" => cli.text = Some(value.clone()),
+                "append" => cli.append = true,
+                "by" => cli.by = Some(value.clone()),
+                "reason" => cli.reason = Some(value.clone()),
+                "at" => cli.at = Some(parse_time(&value).ok_or_else(invalid)?),
+                "due" => due = true,
+                "state" => cli.state = Some(parse_state(&value).ok_or_else(invalid)?),
+                "html" => cli.html = true,
+                _ => unreachable!("option table out of sync"),
+            }
+            given.push(name.to_string());
+        }
+
+        let mut positional = positional.into_iter();
+        let name = positional.next().ok_or(CliError::MissingCommand)?;
+        let mut id = || -> Result<PostId, CliError> {
+            let id = positional.next().ok_or(CliError::MissingArgument("post id"))?;
+            id.parse().map_err(|_| CliError::InvalidValue { option: "ID".to_string(), value: id })
+        };
+        let (name, command): (&'static str, Command) = match name.as_str() {
+            "help" => return Err(CliError::Help),
+            "new" => ("new", Command::New),
+            "edit" => ("edit", Command::Edit(id()?)),
+            "submit" => ("submit", Command::Submit(id()?)),
+            "approve" => ("approve", Command::Approve(id()?)),
+            "reject" => ("reject", Command::Reject(id()?)),
+            "publish" if due => ("publish", Command::PublishDue),
+            "publish" => ("publish", Command::Publish(id()?)),
+            "list" => ("list", Command::List),
+            "show" => ("show", Command::Show(id()?)),
+            _ => return Err(CliError::UnknownCommand(name)),
+        };
+        if let Some(arg) = positional.next() {
+            return Err(CliError::UnexpectedArgument(arg));
+        }
+
+        for option in &given {
+            let allowed = COMMAND_OPTIONS.iter().find(|(o, _)| o == option);
+            if allowed.is_some_and(|(_, commands)| !commands.contains(&name)) {
+                return Err(CliError::NotApplicable { option: option.clone(), command: name });
+            }
+        }
+        if due && cli.at.is_some() {
+            return Err(CliError::NotApplicable { option: "at".to_string(), command: "publish --due" });
+        }
+        match command {
+            Command::Approve(_) | Command::Reject(_) if cli.by.is_none() => {
+                return Err(CliError::Requires { command: name, option: "by" });
+            }
+            Command::Reject(_) if cli.reason.is_none() => {
+                return Err(CliError::Requires { command: name, option: "reason" });
+            }
+            _ => {}
+        }
+
+        cli.command = Some(command);
+        Ok(cli)
+    }
+}
+
+fn parse_time(value: &str) -> Option<SystemTime> {
+    DateTime::parse_from_rfc3339(value).ok().map(SystemTime::from)
+}
+
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+const STATES: &[(&str, State)] = &[
+    ("draft", State::Draft),
+    ("pending-review", State::PendingReview),
+    ("rejected", State::Rejected),
+    ("approved", State::Approved),
+    ("scheduled", State::Scheduled),
+    ("published", State::Published),
+    ("archived", State::Archived),
+];
+
+fn parse_state(value: &str) -> Option<State> {
+    STATES.iter().find(|(name, _)| *name == value).map(|&(_, state)| state)
+}
+
+fn state_name(state: State) -> &'static str {
+    STATES.iter().find(|&&(_, s)| s == state).map_or("unknown", |&(name, _)| name)
+}
+
+/// Runs the command of `cli` against `repository`. Text is read from
+/// `input` when `--text` isn't given; everything is printed to `out`.
+pub fn run(
+    cli: Cli,
+    repository: &dyn PostRepository,
+    input: &mut dyn Read,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let author = || cli.author.clone().ok_or("who is editing? Use --author or set BLOG_AUTHOR");
+    let text = |input: &mut dyn Read| -> Result<String, Box<dyn Error>> {
+        match &cli.text {
+            Some(text) => Ok(text.clone()),
+            None => {
+                let mut text = String::new();
+                input.read_to_string(&mut text)?;
+                Ok(text)
+            }
+        }
+    };
+
+    let command = cli.command.clone().ok_or(CliError::MissingCommand)?;
+    match command {
+        Command::New => {
+            let author = author()?;
+            let mut post = Post::new();
+            post.set_text(&author, &text(input)?)?;
+            writeln!(out, "{}", repository.insert(&post)?)?;
+        }
+        Command::Edit(id) => {
+            let author = author()?;
+            let text = text(input)?;
+            change(repository, id, |post| {
+                if cli.append {
+                    post.add_text(&author, &text)?;
+                } else {
+                    post.set_text(&author, &text)?;
+                }
+                Ok(())
+            })?;
+        }
+        Command::Submit(id) => {
+            change(repository, id, |post| Ok(post.request_review()?))?;
+        }
+        Command::Approve(id) => {
+            let by = cli.by.as_deref().unwrap_or_default();
+            let state = change(repository, id, |post| Ok(post.approve(by)?))?;
+            // Only a post saved with a quorum above one can still be waiting.
+            if state == State::PendingReview {
+                writeln!(out, "approved; waiting for more reviewers")?;
+            }
+        }
+        Command::Reject(id) => {
+            let (by, reason) = (cli.by.as_deref().unwrap_or_default(), cli.reason.as_deref().unwrap_or_default());
+            change(repository, id, |post| Ok(post.reject(by, reason)?))?;
+        }
+        Command::Publish(id) => {
+            change(repository, id, |post| match cli.at {
+                Some(at) => Ok(post.schedule_at(at)?),
+                None => Ok(post.publish()?),
+            })?;
+        }
+        Command::PublishDue => {
+            let now = SystemTime::now();
+            for stored in repository.list()? {
+                if stored.post.is_due(now) {
+                    let mut post = stored.post;
+                    post.publish()?;
+                    repository.update(stored.id, stored.version, &post)?;
+                    writeln!(out, "{}", stored.id)?;
+                }
+            }
+        }
+        Command::List => {
+            for stored in repository.list()? {
+                let state = stored.post.state();
+                if cli.state.is_none_or(|wanted| wanted == state) {
+                    writeln!(out, "{}\t{}\t{}", stored.id, state_name(state), title(&stored.post))?;
+                }
+            }
+        }
+        Command::Show(id) => {
+            let post = repository.get(id)?.post;
+            if cli.html {
+                write!(out, "{}", render::to_html(post.text()))?;
+            } else {
+                show(&post, id, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads post `id`, changes it and saves it back, returning its new state.
+/// If someone saved it in the meantime, that's an error: their change
+/// could be why this one makes sense no more.
+fn change<F>(repository: &dyn PostRepository, id: PostId, change: F) -> Result<State, Box<dyn Error>>
+where
+    F: FnOnce(&mut Post) -> Result<(), Box<dyn Error>>,
+{
+    let mut stored = repository.get(id)?;
+    change(&mut stored.post)?;
+    repository.update(id, stored.version, &stored.post)?;
+    Ok(stored.post.state())
+}
+
+/// The first heading, or else the first line of text.
+fn title(post: &Post) -> String {
+    render::title(post.text())
+        .or_else(|| post.text().lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string))
+        .unwrap_or_else(|| "(untitled)".to_string())
+}
+
+fn show(post: &Post, id: PostId, out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    writeln!(out, "Post {}: {}", id, title(post))?;
+    writeln!(out, "State: {}", state_name(post.state()))?;
+    if let Some(at) = post.publish_at() {
+        writeln!(out, "Publish at: {}", format_time(at))?;
+    }
+    for revision in post.revisions() {
+        writeln!(out, "Revision {}: {} at {}", revision.number, revision.author, format_time(revision.at))?;
+    }
+    for review in post.reviews() {
+        match &review.verdict {
+            Verdict::Approved => writeln!(out, "Review {}: approved by {}", review.round, review.reviewer)?,
+            Verdict::Rejected { reason } => {
+                writeln!(out, "Review {}: rejected by {}: {}", review.round, review.reviewer, reason)?
+            }
+        }
+    }
+    writeln!(out)?;
+    write!(out, "{}", post.text())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::{InMemoryRepository, Workflow};
+
+    fn parse(args: &[&str]) -> Result<Cli, CliError> {
+        Cli::from_iter(std::iter::once(&"blog").chain(args).map(|arg| arg.to_string()))
+    }
+
+    fn blog(repository: &InMemoryRepository, args: &[&str]) -> Result<String, String> {
+        let mut cli = parse(args).map_err(|e| e.to_string())?;
+        cli.author.get_or_insert_with(|| "ann".to_string());
+        let mut out = Vec::new();
+        run(cli, repository, &mut "Hello\n".as_bytes(), &mut out).map_err(|e| e.to_string())?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse(&["list", "--state", "published"]).unwrap().state, Some(State::Published));
+        assert_eq!(parse(&["publish", "--due"]).unwrap().command, Some(Command::PublishDue));
+        assert_eq!(parse(&["show", "3", "--html"]).unwrap().command, Some(Command::Show(3)));
+        assert_eq!(parse(&["--help"]), Err(CliError::Help));
+        assert_eq!(parse(&[]), Err(CliError::MissingCommand));
+        assert_eq!(parse(&["frobnicate"]), Err(CliError::UnknownCommand("frobnicate".into())));
+        assert_eq!(parse(&["show"]), Err(CliError::MissingArgument("post id")));
+        assert_eq!(parse(&["show", "x"]), Err(CliError::InvalidValue { option: "ID".into(), value: "x".into() }));
+        assert_eq!(parse(&["show", "1", "2"]), Err(CliError::UnexpectedArgument("2".into())));
+        assert_eq!(parse(&["list", "--store"]), Err(CliError::MissingValue("--store".into())));
+        assert_eq!(parse(&["approve", "1"]), Err(CliError::Requires { command: "approve", option: "by" }));
+        assert_eq!(
+            parse(&["list", "--html"]),
+            Err(CliError::NotApplicable { option: "html".into(), command: "list" })
+        );
+        assert!(matches!(parse(&["list", "--state=done"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse(&["publish", "1", "--at=tomorrow"]), Err(CliError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn workflow() {
+        let repository = InMemoryRepository::new();
+        assert_eq!(blog(&repository, &["new"]).unwrap(), "1\n");
+        assert_eq!(blog(&repository, &["new", "--text", "# Other"]).unwrap(), "2\n");
+        blog(&repository, &["edit", "1", "--append", "--text=World\n"]).unwrap();
+
+        blog(&repository, &["submit", "1"]).unwrap();
+        assert!(blog(&repository, &["edit", "1", "--text=Late"]).unwrap_err().contains("can't be edited"));
+        blog(&repository, &["reject", "1", "--by=bo", "--reason=Too short"]).unwrap();
+        blog(&repository, &["submit", "1"]).unwrap();
+        blog(&repository, &["approve", "1", "--by=bo"]).unwrap();
+        assert_eq!(blog(&repository, &["list", "--state=approved"]).unwrap(), "1\tapproved\tHello\n");
+        blog(&repository, &["publish", "1"]).unwrap();
+        assert_eq!(blog(&repository, &["list", "--state=published"]).unwrap(), "1\tpublished\tHello\n");
+
+        blog(&repository, &["submit", "2"]).unwrap();
+        assert!(blog(&repository, &["publish", "2"]).is_err());
+        assert_eq!(
+            blog(&repository, &["publish", "2", "--at=2000-01-01T01:00:00+01:00"]).unwrap_err(),
+            "can't Schedule a post in state PendingReview"
+        );
+        assert_eq!(blog(&repository, &["publish", "--due"]).unwrap(), "");
+        blog(&repository, &["approve", "2", "--by=bo"]).unwrap();
+        blog(&repository, &["publish", "2", "--at=2000-01-01T01:00:00+01:00"]).unwrap();
+        assert_eq!(blog(&repository, &["list", "--state=scheduled"]).unwrap(), "2\tscheduled\tOther\n");
+        assert_eq!(blog(&repository, &["publish", "--due"]).unwrap(), "2\n");
+        assert_eq!(blog(&repository, &["publish", "--due"]).unwrap(), "");
+
+        let shown = blog(&repository, &["show", "2"]).unwrap();
+        assert!(shown.starts_with("Post 2: Other\nState: published\nPublish at: 2000-01-01T00:00:00Z\nRevision 1: ann at "));
+        let shown = blog(&repository, &["show", "1"]).unwrap();
+        assert!(shown.ends_with("Review 1: rejected by bo: Too short\nReview 2: approved by bo\n\nHello\nWorld\n"));
+        assert_eq!(blog(&repository, &["show", "1", "--html"]).unwrap(), "<p>Hello\nWorld</p>\n");
+        assert_eq!(blog(&repository, &["show", "9"]).unwrap_err(), "no post 9");
+    }
+
+    #[test]
+    fn quorum() {
+        let repository = InMemoryRepository::new();
+        let mut post = Post::with_workflow(Arc::new(Workflow::standard().with_quorum(2)));
+        post.add_text("ann", "Hello\n").unwrap();
+        post.request_review().unwrap();
+        let id = repository.insert(&post).unwrap().to_string();
+
+        assert_eq!(blog(&repository, &["approve", &id, "--by=bo"]).unwrap(), "approved; waiting for more reviewers\n");
+        assert!(blog(&repository, &["approve", &id, "--by=bo"]).unwrap_err().contains("bo already approved it"));
+        assert_eq!(blog(&repository, &["approve", &id, "--by=carl"]).unwrap(), "");
+        assert_eq!(blog(&repository, &["list", "--state=approved"]).unwrap(), "1\tapproved\tHello\n");
+    }
+}
@@ -1,89 +1,372 @@
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+
+pub mod cli;
+pub mod render;
+pub mod repository;
+pub mod review;
+pub mod revision;
+pub mod typestate;
+pub mod workflow;
+
+pub use repository::{FileRepository, InMemoryRepository, PostId, PostRepository, RepositoryError, StoredPost};
+pub use review::{Review, Verdict};
+pub use revision::{Diff, DiffLine, EditError, Revision};
+pub use typestate::{ApprovedPost, ConversionError, DraftPost, PendingReviewPost, PublishedPost, ScheduledPost};
+pub use workflow::{Action, State, Transition, TransitionError, Workflow};
+
+#[derive(Debug)]
 pub struct Post {
-    state: Option<Box<dyn State>>,
+    state: State,
+    workflow: Arc<Workflow>,
+    /// The content of the latest revision.
     content: String,
+    revisions: Vec<Revision>,
+    reviews: Vec<Review>,
+    /// How many times the post was submitted for review.
+    round: usize,
+    /// When a scheduled post is due.
+    publish_at: Option<SystemTime>,
 }
 
-impl Post {
+/// The one `Workflow::standard` every `Post::new` shares.
+fn standard_workflow() -> Arc<Workflow> {
+    static STANDARD: OnceLock<Arc<Workflow>> = OnceLock::new();
+    Arc::clone(STANDARD.get_or_init(|| Arc::new(Workflow::standard())))
+}
 
+impl Default for Post {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Post {
+    /// A draft following the standard workflow.
     pub fn new() -> Post {
+        Post::with_workflow(standard_workflow())
+    }
+
+    pub fn with_workflow(workflow: Arc<Workflow>) -> Post {
         Post {
-            state: Some(Box::new(Draft{})),
+            state: workflow.initial(),
+            workflow,
             content: String::new(),
+            revisions: Vec::new(),
+            reviews: Vec::new(),
+            round: 0,
+            publish_at: None,
         }
     }
 
-    pub fn add_text(&mut self, text: &str) {
-        self.content.push_str(text);
+    /// Appends `text` to the content as a new revision by `author`,
+    /// returning its number.
+    pub fn add_text(&mut self, author: &str, text: &str) -> Result<usize, EditError> {
+        let content = self.content.clone() + text;
+        self.edit(author, content)
     }
 
-    pub fn content(&self) -> &str {
-        self.state.as_ref().unwrap().content(self)
+    /// Replaces the whole content as a new revision by `author`, returning
+    /// its number.
+    pub fn set_text(&mut self, author: &str, text: &str) -> Result<usize, EditError> {
+        self.edit(author, text.to_string())
+    }
+
+    /// Brings back the content of revision `number`, as a new revision by
+    /// `author`: the history itself is never rewritten.
+    pub fn revert_to(&mut self, number: usize, author: &str) -> Result<usize, EditError> {
+        let content = self.revision(number)?.content.clone();
+        self.edit(author, content)
     }
 
-    pub fn request_review(&mut self) {
-        if let Some(state) = self.state.take() {
-            self.state = Some(state.request_review());
+    fn edit(&mut self, author: &str, content: String) -> Result<usize, EditError> {
+        if !self.state.is_editable() {
+            return Err(EditError::NotEditable(self.state));
         }
+        let number = self.revisions.len() + 1;
+        let at = SystemTime::now();
+        self.content = content.clone();
+        self.revisions.push(Revision { number, author: author.to_string(), at, content });
+        Ok(number)
+    }
+
+    /// Every revision of the content, oldest first.
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    pub fn revision(&self, number: usize) -> Result<&Revision, EditError> {
+        number.checked_sub(1)
+            .and_then(|i| self.revisions.get(i))
+            .ok_or(EditError::NoSuchRevision(number))
     }
 
-    pub fn approve(&mut self) {
-        if let Some(state) = self.state.take() {
-            self.state = Some(state.approve());
+    /// What changed from revision `from` to revision `to`.
+    pub fn diff(&self, from: usize, to: usize) -> Result<Diff<'_>, EditError> {
+        Ok(Diff::new(&self.revision(from)?.content, &self.revision(to)?.content))
+    }
+
+    /// The latest content, whatever the state of the post.
+    pub fn text(&self) -> &str {
+        &self.content
+    }
+
+    /// The text of the post, once it is published. Empty before that.
+    pub fn content(&self) -> &str {
+        match self.state {
+            State::Published => &self.content,
+            _ => "",
         }
     }
-}
 
+    pub fn state(&self) -> State {
+        self.state
+    }
 
-trait State {
-    fn request_review(self: Box<Self>) -> Box<dyn State>;
-    fn approve(self: Box<Self>) -> Box<dyn State>;
-    fn content<'a>(&self, post: &'a Post) -> &'a str;
-}
+    pub(crate) fn set_state(&mut self, state: State) {
+        if state == State::PendingReview && self.state != State::PendingReview {
+            self.round += 1;
+        }
+        self.state = state;
+    }
 
-struct Draft {}
+    /// Every verdict given on the post, oldest first, across submissions.
+    pub fn reviews(&self) -> &[Review] {
+        &self.reviews
+    }
 
-impl State for Draft {
-    fn request_review(self: Box<Self>) -> Box<dyn State> {
-        Box::new(PendingReview {})
+    /// How many reviewers approved the current submission.
+    pub fn approvals(&self) -> usize {
+        self.reviews.iter().filter(|r| r.round == self.round && r.is_approval()).count()
     }
 
-    fn approve(self: Box<Self>) -> Box<dyn State> {
-        self
+    pub fn workflow(&self) -> &Workflow {
+        &self.workflow
     }
 
-    fn content<'a>(&self, _post: &'a Post) -> &'a str {
-        ""
+    /// Whether the post follows the workflow `Post::new` gives it.
+    pub fn has_standard_workflow(&self) -> bool {
+        Arc::ptr_eq(&self.workflow, &standard_workflow())
+    }
+
+    /// Takes `action` if the workflow allows it from the current state,
+    /// returning the new state.
+    pub(crate) fn apply(&mut self, action: Action) -> Result<State, TransitionError> {
+        let workflow = Arc::clone(&self.workflow);
+        workflow.apply(self, action)
+    }
+
+    pub fn request_review(&mut self) -> Result<(), TransitionError> {
+        self.apply(Action::RequestReview).map(drop)
+    }
+
+    /// Records `reviewer`'s approval. Once the workflow's quorum of distinct
+    /// reviewers approved this submission, the post is approved and can be
+    /// published or scheduled.
+    pub fn approve(&mut self, reviewer: &str) -> Result<(), TransitionError> {
+        let (action, from) = (Action::Approve, self.state);
+        if self.workflow.transition(from, action).is_none() {
+            return Err(TransitionError::NotAllowed { action, from });
+        }
+        let round = self.round;
+        if self.reviews.iter().any(|r| r.round == round && r.reviewer == reviewer && r.is_approval()) {
+            let reason = format!("{} already approved it", reviewer);
+            return Err(TransitionError::Refused { action, from, reason });
+        }
+
+        self.reviews.push(Review { reviewer: reviewer.to_string(), verdict: Verdict::Approved, round });
+        if self.approvals() < self.workflow.quorum() {
+            return Ok(());
+        }
+        if let Err(e) = self.apply(action) {
+            self.reviews.pop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Sends the post back to its author, with `reason` kept in the reviews.
+    /// A single reviewer is enough. The post is then `State::Rejected`,
+    /// which is editable and can be submitted again like a draft.
+    pub fn reject(&mut self, reviewer: &str, reason: &str) -> Result<(), TransitionError> {
+        let round = self.round;
+        let verdict = Verdict::Rejected { reason: reason.to_string() };
+        self.reviews.push(Review { reviewer: reviewer.to_string(), verdict, round });
+        if let Err(e) = self.apply(Action::Reject) {
+            self.reviews.pop();
+            return Err(e);
+        }
+        Ok(())
     }
-}
 
-struct PendingReview {}
+    pub fn schedule(&mut self) -> Result<(), TransitionError> {
+        self.apply(Action::Schedule).map(drop)
+    }
+
+    /// Schedules the post for publication at `time`. Nothing publishes it
+    /// by itself: see `is_due`.
+    pub fn schedule_at(&mut self, time: SystemTime) -> Result<(), TransitionError> {
+        self.schedule()?;
+        self.publish_at = Some(time);
+        Ok(())
+    }
+
+    /// When the post was scheduled to be published, if it was given a time.
+    pub fn publish_at(&self) -> Option<SystemTime> {
+        self.publish_at
+    }
 
-impl State for PendingReview {
-    fn request_review(self: Box<Self>) -> Box<dyn State> {
-        self
+    /// Whether the post is scheduled for `now` or earlier.
+    pub fn is_due(&self, now: SystemTime) -> bool {
+        self.state == State::Scheduled && self.publish_at.is_some_and(|at| at <= now)
     }
 
-    fn approve(self: Box<Self>) -> Box<dyn State> {
-        Box::new(Published {})
+    pub fn publish(&mut self) -> Result<(), TransitionError> {
+        self.apply(Action::Publish).map(drop)
     }
 
-    fn content<'a>(&self, _post: &'a Post) -> &'a str {
-        ""
+    pub fn archive(&mut self) -> Result<(), TransitionError> {
+        self.apply(Action::Archive).map(drop)
     }
 }
 
-struct Published {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn standard_workflow() {
+        let mut post = Post::new();
+        post.add_text("ann", "I ate a salad for lunch today").unwrap();
+        assert_eq!(post.state(), State::Draft);
+        assert_eq!(post.content(), "");
+
+        assert_eq!(
+            post.approve("alice"),
+            Err(TransitionError::NotAllowed { action: Action::Approve, from: State::Draft })
+        );
+        assert_eq!(post.state(), State::Draft);
+        assert!(post.reviews().is_empty());
+
+        post.request_review().unwrap();
+        post.reject("alice", "too short").unwrap();
+        assert_eq!(post.state(), State::Rejected);
+        post.request_review().unwrap();
+        post.approve("alice").unwrap();
+        assert_eq!(post.state(), State::Approved);
+        post.schedule().unwrap();
+        assert_eq!(post.content(), "");
+        post.publish().unwrap();
+        assert_eq!(post.content(), "I ate a salad for lunch today");
 
-impl State for Published {
-    fn request_review(self: Box<Self>) -> Box<dyn State> {
-        self
+        assert!(post.request_review().is_err());
+        post.archive().unwrap();
+        assert_eq!(post.state(), State::Archived);
+        assert_eq!(post.workflow().actions(State::Archived), []);
     }
-    
-    fn approve(self: Box<Self>) -> Box<dyn State> {
-        self
+
+    #[test]
+    fn unreviewed() {
+        let mut post = Post::with_workflow(Arc::new(Workflow::standard().with_quorum(2)));
+        post.add_text("ann", "Hello").unwrap();
+        post.request_review().unwrap();
+
+        let (schedule, publish) = (Action::Schedule, Action::Publish);
+        let from = State::PendingReview;
+        assert_eq!(post.schedule_at(SystemTime::now()), Err(TransitionError::NotAllowed { action: schedule, from }));
+        assert_eq!(post.publish(), Err(TransitionError::NotAllowed { action: publish, from }));
+        assert_eq!(post.publish_at(), None);
+
+        // Not even the workflow itself skips the quorum.
+        post.approve("alice").unwrap();
+        assert_eq!(
+            post.apply(Action::Approve),
+            Err(TransitionError::Refused { action: Action::Approve, from, reason: "1 of 2 approvals".into() })
+        );
+        assert_eq!(post.state(), State::PendingReview);
     }
 
-    fn content<'a>(&self, post: &'a Post) -> &'a str {
-        &post.content
+    #[test]
+    fn guards_and_hooks() {
+        static PUBLISHED: AtomicUsize = AtomicUsize::new(0);
+
+        let workflow = Workflow::new(State::Draft)
+            .with(
+                Transition::new(Action::RequestReview, State::Draft, State::PendingReview)
+                    .guard(|post| if post.content.is_empty() { Err("nothing to review".into()) } else { Ok(()) }),
+            )
+            .with(
+                Transition::new(Action::Approve, State::PendingReview, State::Published)
+                    .hook(|_| {
+                        PUBLISHED.fetch_add(1, Ordering::SeqCst);
+                    }),
+            );
+        let mut post = Post::with_workflow(Arc::new(workflow));
+
+        assert_eq!(
+            post.request_review(),
+            Err(TransitionError::Refused {
+                action: Action::RequestReview,
+                from: State::Draft,
+                reason: "nothing to review".into(),
+            })
+        );
+        post.add_text("ann", "Hello").unwrap();
+        post.request_review().unwrap();
+        post.approve("alice").unwrap();
+        assert_eq!(PUBLISHED.load(Ordering::SeqCst), 1);
+        assert_eq!(post.content(), "Hello");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn quorum() {
+        let mut post = Post::with_workflow(Arc::new(Workflow::standard().with_quorum(2)));
+        post.add_text("ann", "Hello").unwrap();
+        post.request_review().unwrap();
+
+        post.approve("alice").unwrap();
+        assert_eq!(post.state(), State::PendingReview);
+        assert!(matches!(post.approve("alice"), Err(TransitionError::Refused { .. })));
+        assert_eq!(post.approvals(), 1);
+
+        // A rejection sends it back; the next submission starts over.
+        post.reject("bob", "needs a title").unwrap();
+        assert_eq!(post.state(), State::Rejected);
+        post.request_review().unwrap();
+        assert_eq!(post.approvals(), 0);
+
+        post.approve("alice").unwrap();
+        post.approve("bob").unwrap();
+        assert_eq!(post.state(), State::Approved);
+
+        let history: Vec<_> = post.reviews().iter().map(|r| (r.reviewer.as_str(), r.round, r.is_approval())).collect();
+        assert_eq!(history, [("alice", 1, true), ("bob", 1, false), ("alice", 2, true), ("bob", 2, true)]);
+        assert_eq!(post.reviews()[1].verdict, Verdict::Rejected { reason: "needs a title".into() });
+
+        assert!(post.reject("carol", "too late").is_err());
+        assert_eq!(post.reviews().len(), 4);
+    }
+
+    #[test]
+    fn revisions() {
+        let mut post = Post::new();
+        assert_eq!(post.add_text("ann", "Lunch\n").unwrap(), 1);
+        assert_eq!(post.add_text("bo", "I ate a salad\n").unwrap(), 2);
+        assert_eq!(post.set_text("ann", "Lunch\nI ate a sandwich\n").unwrap(), 3);
+        assert_eq!(post.diff(2, 3).unwrap().to_string(), " Lunch\n-I ate a salad\n+I ate a sandwich\n");
+        assert_eq!(post.diff(1, 4), Err(EditError::NoSuchRevision(4)));
+
+        post.request_review().unwrap();
+        assert_eq!(post.add_text("ann", "!"), Err(EditError::NotEditable(State::PendingReview)));
+        post.reject("carl", "Salad was better").unwrap();
+
+        assert_eq!(post.revert_to(2, "ann").unwrap(), 4);
+        assert_eq!(post.text(), "Lunch\nI ate a salad\n");
+        assert!(post.diff(2, 4).unwrap().is_empty());
+        let authors: Vec<_> = post.revisions().iter().map(|r| r.author.as_str()).collect();
+        assert_eq!(authors, ["ann", "bo", "ann", "ann"]);
+        assert_eq!(post.revision(0), Err(EditError::NoSuchRevision(0)));
+    }
+}
@@ -1,14 +1,34 @@
-use blog::Post;
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::process;
 
-fn main() {
-    let mut post = Post::new();
-
-    post.add_text("I ate a salad for lunch today");
-    assert_eq!("", post.content());
+use blog::cli::{self, Cli, CliError};
+use blog::FileRepository;
 
-    post.request_review();
-    assert_eq!("", post.content());
+fn main() {
+    let mut cli = Cli::from_iter(env::args()).unwrap_or_else(|err| match err {
+        CliError::Help => {
+            print!("{}", cli::USAGE);
+            process::exit(0);
+        }
+        err => {
+            eprintln!("blog: {}", err);
+            eprintln!("Try 'blog help' for more information.");
+            process::exit(2);
+        }
+    });
+    if cli.author.is_none() {
+        cli.author = env::var("BLOG_AUTHOR").or_else(|_| env::var("USER")).ok();
+    }
+    let store = cli.store.clone()
+        .or_else(|| env::var_os("BLOG_STORE").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("posts.json"));
 
-    post.approve();
-    assert_eq!("I ate a salad for lunch today", post.content());
+    let repository = FileRepository::new(store);
+    let stdout = io::stdout();
+    if let Err(e) = cli::run(cli, &repository, &mut io::stdin(), &mut stdout.lock()) {
+        eprintln!("blog: {}", e);
+        process::exit(1);
+    }
 }
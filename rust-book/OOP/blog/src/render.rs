@@ -0,0 +1,237 @@
+//! Turns the Markdown content of published posts into HTML.
+//!
+//! Posts are written by people but read in browsers, so the HTML is
+//! sanitized as it is generated: HTML written in a post is shown as text
+//! rather than passed through, and links or images pointing at anything but
+//! `http`, `https`, `mailto` or a relative URL lose their destination.
+
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+
+use crate::{Post, State, StoredPost};
+
+/// How long an excerpt on the index page can get, in characters.
+pub const EXCERPT_LEN: usize = 200;
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH)
+}
+
+/// Renders `markdown` as sanitized HTML.
+pub fn to_html(markdown: &str) -> String {
+    let events = parser(markdown).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+            Event::Start(Tag::Link { link_type, dest_url: safe_url(dest_url), title, id })
+        }
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+            Event::Start(Tag::Image { link_type, dest_url: safe_url(dest_url), title, id })
+        }
+        event => event,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    out
+}
+
+/// The URL if following it can only navigate somewhere, an empty one
+/// otherwise (`javascript:` and the like).
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    let end = url.find(['/', '?', '#']).unwrap_or(url.len());
+    let scheme = match url[..end].find(':') {
+        Some(colon) => &url[..colon],
+        None => return url,
+    };
+    // Browsers ignore these anywhere in the scheme.
+    let scheme: String = scheme.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    if ["http", "https", "mailto"].contains(&scheme.to_ascii_lowercase().as_str()) {
+        url
+    } else {
+        CowStr::Borrowed("")
+    }
+}
+
+/// The post's content as HTML, once it is published.
+pub fn render(post: &Post) -> Option<String> {
+    match post.state() {
+        State::Published => Some(to_html(post.content())),
+        _ => None,
+    }
+}
+
+/// The text of the first heading, if there is one.
+pub fn title(markdown: &str) -> Option<String> {
+    let mut title = None;
+    for event in parser(markdown) {
+        match event {
+            Event::Start(Tag::Heading { .. }) => title = Some(String::new()),
+            Event::End(TagEnd::Heading(_)) => break,
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(title) = &mut title {
+                    title.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+    title.filter(|title| !title.trim().is_empty())
+}
+
+/// The first paragraph as plain text, cut after at most `max_len`
+/// characters at the end of a word.
+pub fn excerpt(markdown: &str, max_len: usize) -> String {
+    let mut text = String::new();
+    let mut in_paragraph = false;
+    for event in parser(markdown) {
+        match event {
+            Event::Start(Tag::Paragraph) => in_paragraph = true,
+            Event::End(TagEnd::Paragraph) => break,
+            Event::Text(t) | Event::Code(t) if in_paragraph => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak if in_paragraph => text.push(' '),
+            _ => {}
+        }
+    }
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut excerpt = String::new();
+    for word in &words {
+        let len = excerpt.chars().count() + usize::from(!excerpt.is_empty()) + word.chars().count();
+        if len > max_len {
+            excerpt.push('…');
+            return excerpt;
+        }
+        if !excerpt.is_empty() {
+            excerpt.push(' ');
+        }
+        excerpt.push_str(word);
+    }
+    excerpt
+}
+
+/// Writes every published post of `posts` to `dir` as `<id>.html`, and an
+/// `index.html` linking to them, newest first. Returns how many posts were
+/// written.
+pub fn export(posts: &[StoredPost], dir: &Path) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+    let mut published: Vec<&StoredPost> = posts.iter().filter(|p| p.post.state() == State::Published).collect();
+    published.sort_by_key(|p| Reverse(p.id));
+
+    let mut index = String::new();
+    for stored in &published {
+        let content = stored.post.content();
+        let title = post_title(stored);
+        let body = to_html(content);
+        fs::write(dir.join(format!("{}.html", stored.id)), page(&title, &body))?;
+
+        index.push_str(&format!(
+            "<article>\n<h2><a href=\"{}.html\">{}</a></h2>\n<p>{}</p>\n</article>\n",
+            stored.id,
+            escape(&title),
+            escape(&excerpt(content, EXCERPT_LEN)),
+        ));
+    }
+    fs::write(dir.join("index.html"), page("Posts", &index))?;
+    Ok(published.len())
+}
+
+fn post_title(stored: &StoredPost) -> String {
+    title(stored.post.content()).unwrap_or_else(|| format!("Post {}", stored.id))
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        body,
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{InMemoryRepository, PostRepository};
+
+    const POST: &str = "# Lunch & *dinner*\n\nI ate a [salad](https://example.com/salad)\nfor lunch today.\n\n\
+                        - one\n- two\n\n```rust\nlet x = 1 < 2;\n```\n";
+
+    #[test]
+    fn markdown() {
+        let html = to_html(POST);
+        assert!(html.contains("<h1>Lunch &amp; <em>dinner</em></h1>"));
+        assert!(html.contains("<a href=\"https://example.com/salad\">salad</a>"));
+        assert!(html.contains("<ul>\n<li>one</li>\n<li>two</li>\n</ul>"));
+        assert!(html.contains("<pre><code class=\"language-rust\">let x = 1 &lt; 2;\n</code></pre>"));
+    }
+
+    #[test]
+    fn sanitized() {
+        let html = to_html("<script>alert(1)</script>\n\nHi <img src=x onerror=alert(1)>");
+        assert!(!html.contains("<script") && !html.contains("<img"), "{}", html);
+        assert!(html.contains("&lt;script&gt;"));
+
+        for url in ["javascript:alert(1)", "JavaScript:alert(1)", "java&#9;script:alert(1)", "data:text/html,x"] {
+            let html = to_html(&format!("[x]({}) ![y]({})", url, url));
+            assert_eq!(html, "<p><a href=\"\">x</a> <img src=\"\" alt=\"y\" /></p>\n", "{}", url);
+        }
+        for url in ["/about", "post.html#top", "mailto:ann@example.com", "http://example.com/a:b", "?q=a:b"] {
+            assert!(to_html(&format!("[x]({})", url)).contains(&format!("href=\"{}\"", url)), "{}", url);
+        }
+    }
+
+    #[test]
+    fn title_and_excerpt() {
+        assert_eq!(title(POST).as_deref(), Some("Lunch & dinner"));
+        assert_eq!(title("No heading"), None);
+        assert_eq!(excerpt(POST, 200), "I ate a salad for lunch today.");
+        assert_eq!(excerpt(POST, 12), "I ate a…");
+        assert_eq!(excerpt("# Only a title", 200), "");
+    }
+
+    #[test]
+    fn export_site() {
+        let repository = InMemoryRepository::new();
+        for text in [POST, "Not published"] {
+            let mut post = Post::new();
+            post.add_text("ann", text).unwrap();
+            post.request_review().unwrap();
+            if text == POST {
+                post.approve("bo").unwrap();
+                post.publish().unwrap();
+            }
+            repository.insert(&post).unwrap();
+        }
+        assert_eq!(render(&repository.get(2).unwrap().post), None);
+
+        let dir = std::env::temp_dir().join(format!("blog-export-{}", std::process::id()));
+        assert_eq!(export(&repository.list().unwrap(), &dir).unwrap(), 1);
+        let index = fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"1.html\">Lunch &amp; dinner</a>"));
+        assert!(index.contains("<p>I ate a salad for lunch today.</p>"));
+        let page = fs::read_to_string(dir.join("1.html")).unwrap();
+        assert!(page.contains("<title>Lunch &amp; dinner</title>"));
+        assert!(page.contains("<h1>Lunch &amp; <em>dinner</em></h1>"));
+        assert!(!dir.join("2.html").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Post, Review, Revision, State, Workflow};
+
+pub type PostId = u64;
+
+/// A post as a repository hands it out: `version` goes up with every save,
+/// and must be given back to save again.
+#[derive(Debug)]
+pub struct StoredPost {
+    pub id: PostId,
+    pub version: u64,
+    pub post: Post,
+}
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    NotFound(PostId),
+    /// Someone else saved the post since it was read: `expected` is the
+    /// version the caller read, `actual` the one stored now.
+    Conflict { id: PostId, expected: u64, actual: u64 },
+    Io(io::Error),
+    /// The store doesn't hold what was written to it.
+    Corrupt(serde_json::Error),
+    /// The post follows a workflow of its own, which can't be stored.
+    CustomWorkflow,
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::NotFound(id) => write!(f, "no post {}", id),
+            RepositoryError::Conflict { id, expected, actual } => write!(
+                f,
+                "post {} was changed by someone else (version {}, expected {})",
+                id, actual, expected
+            ),
+            RepositoryError::Io(e) => e.fmt(f),
+            RepositoryError::Corrupt(e) => write!(f, "corrupt post store: {}", e),
+            RepositoryError::CustomWorkflow => write!(f, "only posts following the standard workflow can be stored"),
+        }
+    }
+}
+
+impl Error for RepositoryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RepositoryError::Io(e) => Some(e),
+            RepositoryError::Corrupt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RepositoryError {
+    fn from(e: io::Error) -> Self {
+        RepositoryError::Io(e)
+    }
+}
+
+/// Where posts are kept between runs.
+///
+/// Saving is optimistic: `update` and `delete` take the version the post
+/// was read at and fail with `RepositoryError::Conflict` if it has been
+/// saved since, so two editors can't overwrite each other's work. The
+/// loser reads the post again and retries.
+///
+/// Workflows are code, not data: only posts following `Workflow::standard`
+/// are stored, and they come back with the quorum they were saved with.
+/// Others are refused with `RepositoryError::CustomWorkflow`.
+pub trait PostRepository {
+    /// Stores a new post, returning its id. Its version is 1.
+    fn insert(&self, post: &Post) -> Result<PostId, RepositoryError>;
+
+    fn get(&self, id: PostId) -> Result<StoredPost, RepositoryError>;
+
+    /// Every post, by id.
+    fn list(&self) -> Result<Vec<StoredPost>, RepositoryError>;
+
+    /// Replaces post `id`, provided it's still at `version`. Returns the
+    /// new version.
+    fn update(&self, id: PostId, version: u64, post: &Post) -> Result<u64, RepositoryError>;
+
+    fn delete(&self, id: PostId, version: u64) -> Result<(), RepositoryError>;
+}
+
+/// What is stored of a post.
+#[derive(Clone, Serialize, Deserialize)]
+struct Record {
+    id: PostId,
+    version: u64,
+    state: State,
+    content: String,
+    revisions: Vec<Revision>,
+    reviews: Vec<Review>,
+    round: usize,
+    #[serde(default)]
+    publish_at: Option<SystemTime>,
+    #[serde(default = "single_approval")]
+    quorum: usize,
+}
+
+fn single_approval() -> usize {
+    1
+}
+
+impl Record {
+    fn new(id: PostId, version: u64, post: &Post) -> Result<Record, RepositoryError> {
+        if !post.workflow.is_standard() {
+            return Err(RepositoryError::CustomWorkflow);
+        }
+        Ok(Record {
+            id,
+            version,
+            state: post.state,
+            content: post.content.clone(),
+            revisions: post.revisions.clone(),
+            reviews: post.reviews.clone(),
+            round: post.round,
+            publish_at: post.publish_at,
+            quorum: post.workflow.quorum(),
+        })
+    }
+
+    fn load(self) -> StoredPost {
+        let mut post = match self.quorum {
+            0 | 1 => Post::new(),
+            quorum => Post::with_workflow(Arc::new(Workflow::standard().with_quorum(quorum))),
+        };
+        post.state = self.state;
+        post.content = self.content;
+        post.revisions = self.revisions;
+        post.reviews = self.reviews;
+        post.round = self.round;
+        post.publish_at = self.publish_at;
+        StoredPost { id: self.id, version: self.version, post }
+    }
+}
+
+/// Every post of a store, the unit both repositories read and write.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Posts {
+    next_id: PostId,
+    posts: BTreeMap<PostId, Record>,
+}
+
+impl Posts {
+    fn insert(&mut self, post: &Post) -> Result<PostId, RepositoryError> {
+        let record = Record::new(self.next_id + 1, 1, post)?;
+        self.next_id += 1;
+        self.posts.insert(self.next_id, record);
+        Ok(self.next_id)
+    }
+
+    fn get(&self, id: PostId) -> Result<StoredPost, RepositoryError> {
+        self.posts.get(&id).cloned().map(Record::load).ok_or(RepositoryError::NotFound(id))
+    }
+
+    fn list(&self) -> Vec<StoredPost> {
+        self.posts.values().cloned().map(Record::load).collect()
+    }
+
+    fn check(&self, id: PostId, version: u64) -> Result<(), RepositoryError> {
+        let actual = self.posts.get(&id).ok_or(RepositoryError::NotFound(id))?.version;
+        if actual != version {
+            return Err(RepositoryError::Conflict { id, expected: version, actual });
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, id: PostId, version: u64, post: &Post) -> Result<u64, RepositoryError> {
+        self.check(id, version)?;
+        self.posts.insert(id, Record::new(id, version + 1, post)?);
+        Ok(version + 1)
+    }
+
+    fn delete(&mut self, id: PostId, version: u64) -> Result<(), RepositoryError> {
+        self.check(id, version)?;
+        self.posts.remove(&id);
+        Ok(())
+    }
+}
+
+/// Keeps posts for as long as it lives. Handy for tests.
+#[derive(Default)]
+pub struct InMemoryRepository {
+    posts: Mutex<Posts>,
+}
+
+impl InMemoryRepository {
+    pub fn new() -> InMemoryRepository {
+        InMemoryRepository::default()
+    }
+
+    fn posts(&self) -> std::sync::MutexGuard<'_, Posts> {
+        // Nothing can panic halfway through a change: the data is fine.
+        self.posts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl PostRepository for InMemoryRepository {
+    fn insert(&self, post: &Post) -> Result<PostId, RepositoryError> {
+        self.posts().insert(post)
+    }
+
+    fn get(&self, id: PostId) -> Result<StoredPost, RepositoryError> {
+        self.posts().get(id)
+    }
+
+    fn list(&self) -> Result<Vec<StoredPost>, RepositoryError> {
+        Ok(self.posts().list())
+    }
+
+    fn update(&self, id: PostId, version: u64, post: &Post) -> Result<u64, RepositoryError> {
+        self.posts().update(id, version, post)
+    }
+
+    fn delete(&self, id: PostId, version: u64) -> Result<(), RepositoryError> {
+        self.posts().delete(id, version)
+    }
+}
+
+/// How long to wait for another process to finish writing the store.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Keeps every post in one JSON file, which needn't exist yet.
+///
+/// Each change reads the file, applies itself and writes the file back
+/// atomically, holding a lock on a file next to it meanwhile, so several
+/// processes can share a store. The lock is the system's: it goes away with
+/// a process that crashed.
+pub struct FileRepository {
+    path: PathBuf,
+}
+
+impl FileRepository {
+    pub fn new(path: impl Into<PathBuf>) -> FileRepository {
+        FileRepository { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self) -> Result<Posts, RepositoryError> {
+        match fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(RepositoryError::Corrupt),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Posts::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Locks the store until the returned file is dropped. The lock file
+    /// itself stays.
+    fn lock(&self) -> io::Result<File> {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".lock");
+        let path = PathBuf::from(path);
+        let context = |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", path.display(), e));
+
+        let file = OpenOptions::new().write(true).create(true).truncate(false).open(&path).map_err(context)?;
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(file),
+                Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(context(io::Error::new(io::ErrorKind::TimedOut, "the store is still locked")));
+                }
+                Err(TryLockError::Error(e)) => return Err(context(e)),
+            }
+        }
+    }
+
+    /// Applies `change` to the posts under the lock, then writes them back.
+    fn change<T>(&self, change: impl FnOnce(&mut Posts) -> Result<T, RepositoryError>) -> Result<T, RepositoryError> {
+        let _lock = self.lock()?;
+        let mut posts = self.read()?;
+        let result = change(&mut posts)?;
+        let json = serde_json::to_vec_pretty(&posts).map_err(RepositoryError::Corrupt)?;
+        write_atomically(&self.path, &json)?;
+        Ok(result)
+    }
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over
+/// `path`, so readers see either the old file or the new one, never half of
+/// it.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.blog-{}.tmp", name, process::id()));
+
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+impl PostRepository for FileRepository {
+    fn insert(&self, post: &Post) -> Result<PostId, RepositoryError> {
+        self.change(|posts| posts.insert(post))
+    }
+
+    fn get(&self, id: PostId) -> Result<StoredPost, RepositoryError> {
+        self.read()?.get(id)
+    }
+
+    fn list(&self) -> Result<Vec<StoredPost>, RepositoryError> {
+        Ok(self.read()?.list())
+    }
+
+    fn update(&self, id: PostId, version: u64, post: &Post) -> Result<u64, RepositoryError> {
+        self.change(|posts| posts.update(id, version, post))
+    }
+
+    fn delete(&self, id: PostId, version: u64) -> Result<(), RepositoryError> {
+        self.change(|posts| posts.delete(id, version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{Action, Transition};
+
+    fn exercise(repository: &dyn PostRepository) {
+        let mut post = Post::new();
+        post.add_text("ann", "Hello\n").unwrap();
+        let id = repository.insert(&post).unwrap();
+        assert_eq!(repository.insert(&Post::new()).unwrap(), id + 1);
+
+        // Two editors read the same version...
+        let mut first = repository.get(id).unwrap();
+        let mut second = repository.get(id).unwrap();
+        assert_eq!(first.version, 1);
+        assert_eq!(first.post.text(), "Hello\n");
+
+        // ...the first to save wins.
+        first.post.request_review().unwrap();
+        assert_eq!(repository.update(id, first.version, &first.post).unwrap(), 2);
+        second.post.add_text("bo", "World\n").unwrap();
+        assert!(matches!(
+            repository.update(id, second.version, &second.post),
+            Err(RepositoryError::Conflict { expected: 1, actual: 2, .. })
+        ));
+
+        let mut stored = repository.get(id).unwrap();
+        assert_eq!(stored.post.state(), State::PendingReview);
+        stored.post.approve("carl").unwrap();
+        stored.post.publish().unwrap();
+        repository.update(id, stored.version, &stored.post).unwrap();
+
+        let stored = repository.get(id).unwrap();
+        assert_eq!(stored.post.content(), "Hello\n");
+        assert_eq!(stored.post.revisions().len(), 1);
+        assert_eq!(stored.post.reviews()[0].reviewer, "carl");
+
+        // Transitions are code: a post with its own can't be stored.
+        let workflow = Workflow::standard().with(Transition::new(Action::Publish, State::Draft, State::Published));
+        let custom = Post::with_workflow(Arc::new(workflow));
+        assert!(matches!(repository.insert(&custom), Err(RepositoryError::CustomWorkflow)));
+        assert!(matches!(repository.update(id, 3, &custom), Err(RepositoryError::CustomWorkflow)));
+
+        assert!(matches!(repository.delete(id, 1), Err(RepositoryError::Conflict { .. })));
+        repository.delete(id, 3).unwrap();
+        assert!(matches!(repository.get(id), Err(RepositoryError::NotFound(_))));
+        assert_eq!(repository.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn in_memory() {
+        exercise(&InMemoryRepository::new());
+    }
+
+    #[test]
+    fn file() {
+        let root = std::env::temp_dir().join(format!("blog-repository-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("posts.json");
+
+        // A lock file left behind, as by a crash, doesn't lock anything.
+        fs::write(root.join("posts.json.lock"), "").unwrap();
+        let repository = FileRepository::new(&path);
+        exercise(&repository);
+        // Another repository on the same file sees the same posts.
+        let posts = FileRepository::new(&path).list().unwrap();
+        assert_eq!(posts.iter().map(|p| p.id).collect::<Vec<_>>(), [2]);
+
+        // Posts needing several approvals keep needing them.
+        let mut post = Post::with_workflow(Arc::new(Workflow::standard().with_quorum(2)));
+        post.add_text("ann", "Hello\n").unwrap();
+        post.request_review().unwrap();
+        post.approve("bo").unwrap();
+        let id = repository.insert(&post).unwrap();
+        let mut stored = FileRepository::new(&path).get(id).unwrap();
+        assert_eq!(stored.post.workflow().quorum(), 2);
+        assert!(stored.post.approve("bo").is_err());
+        stored.post.approve("carl").unwrap();
+        assert_eq!(stored.post.state(), State::Approved);
+        assert_eq!(repository.get(2).unwrap().post.workflow().quorum(), 1);
+
+        fs::write(&path, "not json").unwrap();
+        assert!(matches!(FileRepository::new(&path).list(), Err(RepositoryError::Corrupt(_))));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
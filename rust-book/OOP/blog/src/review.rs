@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// What a reviewer decided.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verdict {
+    Approved,
+    /// Sent back to the author, with what needs changing.
+    Rejected { reason: String },
+}
+
+/// One reviewer's verdict on one submission of a post.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Review {
+    pub reviewer: String,
+    pub verdict: Verdict,
+    /// Which submission this was about: 1 for the first request for review,
+    /// 2 once the post was resubmitted after a rejection, and so on.
+    pub round: usize,
+}
+
+impl Review {
+    pub fn is_approval(&self) -> bool {
+        self.verdict == Verdict::Approved
+    }
+}
@@ -0,0 +1,120 @@
+use std::error::Error;
+use std::fmt;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+
+/// The content of a post as one edit left it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    /// Starts at 1 and goes up with every edit.
+    pub number: usize,
+    pub author: String,
+    pub at: SystemTime,
+    pub content: String,
+}
+
+/// Why the content of a post could not be edited or compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// Only drafts and rejected posts can be edited.
+    NotEditable(State),
+    NoSuchRevision(usize),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NotEditable(state) => write!(f, "a post in state {:?} can't be edited", state),
+            EditError::NoSuchRevision(number) => write!(f, "no revision {}", number),
+        }
+    }
+}
+
+impl Error for EditError {}
+
+/// One line of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Added(&'a str),
+    Removed(&'a str),
+}
+
+/// The lines that changed between two revisions. Displays like the body
+/// of a unified diff, every line prefixed with ` `, `+` or `-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff<'a> {
+    pub lines: Vec<DiffLine<'a>>,
+}
+
+impl<'a> Diff<'a> {
+    /// Compares `old` and `new` line by line, keeping the longest run of
+    /// lines they have in common.
+    pub fn new(old: &'a str, new: &'a str) -> Diff<'a> {
+        let old: Vec<&str> = old.lines().collect();
+        let new: Vec<&str> = new.lines().collect();
+
+        // common[i][j]: how many lines old[i..] and new[j..] have in common.
+        let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                common[i][j] = if old[i] == new[j] {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    common[i + 1][j].max(common[i][j + 1])
+                };
+            }
+        }
+
+        let mut lines = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i] == new[j] {
+                lines.push(DiffLine::Same(old[i]));
+                i += 1;
+                j += 1;
+            } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+                lines.push(DiffLine::Removed(old[i]));
+                i += 1;
+            } else {
+                lines.push(DiffLine::Added(new[j]));
+                j += 1;
+            }
+        }
+        Diff { lines }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|line| matches!(line, DiffLine::Same(_)))
+    }
+}
+
+impl fmt::Display for Diff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                DiffLine::Same(text) => writeln!(f, " {}", text)?,
+                DiffLine::Added(text) => writeln!(f, "+{}", text)?,
+                DiffLine::Removed(text) => writeln!(f, "-{}", text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff() {
+        let diff = Diff::new("a\nb\nc\nd\n", "a\nc\nx\nd\ne\n");
+        assert_eq!(diff.to_string(), " a\n-b\n c\n+x\n d\n+e\n");
+        assert!(!diff.is_empty());
+        assert!(Diff::new("same\n", "same").is_empty());
+        assert_eq!(Diff::new("", "new").lines, [DiffLine::Added("new")]);
+    }
+}
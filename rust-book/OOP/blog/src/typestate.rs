@@ -0,0 +1,308 @@
+//! The same workflow with each state as its own type, so that misusing a
+//! post is a compile error rather than an `Err` or an empty string.
+//!
+//! Every transition consumes the post and returns it as the type of its new
+//! state:
+//!
+//! ```
+//! use blog::DraftPost;
+//!
+//! let mut post = DraftPost::new();
+//! post.add_text("ann", "I ate a salad for lunch today");
+//! let post = post.request_review().approve("bo").publish();
+//! assert_eq!(post.content(), "I ate a salad for lunch today");
+//! ```
+//!
+//! These types always follow `Workflow::standard`, whose transitions have
+//! no guards and need a single approval, so none of them can fail. They
+//! convert to and from `Post` when the dynamic API is more convenient.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::time::SystemTime;
+
+use crate::{Post, State, TransitionError};
+
+/// A post that can still be edited: a draft, or one a reviewer rejected.
+#[derive(Debug)]
+pub struct DraftPost {
+    post: Post,
+}
+
+/// A post waiting for its review.
+#[derive(Debug)]
+pub struct PendingReviewPost {
+    post: Post,
+}
+
+/// A post its reviewer approved, to be published now or later.
+#[derive(Debug)]
+pub struct ApprovedPost {
+    post: Post,
+}
+
+/// An approved post waiting for its publication time.
+#[derive(Debug)]
+pub struct ScheduledPost {
+    post: Post,
+}
+
+#[derive(Debug)]
+pub struct PublishedPost {
+    post: Post,
+}
+
+/// Only the standard workflow's transitions are taken here, and they can't
+/// be refused.
+fn allowed(result: Result<(), TransitionError>) {
+    if let Err(e) = result {
+        unreachable!("the standard workflow refused a transition: {}", e);
+    }
+}
+
+impl Default for DraftPost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DraftPost {
+    pub fn new() -> DraftPost {
+        DraftPost { post: Post::new() }
+    }
+
+    /// Appends `text` as a new revision by `author`, returning its number.
+    pub fn add_text(&mut self, author: &str, text: &str) -> usize {
+        self.post.add_text(author, text).expect("a draft is editable")
+    }
+
+    /// Replaces the content as a new revision by `author`, returning its
+    /// number.
+    pub fn set_text(&mut self, author: &str, text: &str) -> usize {
+        self.post.set_text(author, text).expect("a draft is editable")
+    }
+
+    pub fn text(&self) -> &str {
+        self.post.text()
+    }
+
+    pub fn request_review(mut self) -> PendingReviewPost {
+        allowed(self.post.request_review());
+        PendingReviewPost { post: self.post }
+    }
+}
+
+impl PendingReviewPost {
+    pub fn text(&self) -> &str {
+        self.post.text()
+    }
+
+    pub fn approve(mut self, reviewer: &str) -> ApprovedPost {
+        allowed(self.post.approve(reviewer));
+        ApprovedPost { post: self.post }
+    }
+
+    pub fn reject(mut self, reviewer: &str, reason: &str) -> DraftPost {
+        allowed(self.post.reject(reviewer, reason));
+        DraftPost { post: self.post }
+    }
+}
+
+impl ApprovedPost {
+    pub fn text(&self) -> &str {
+        self.post.text()
+    }
+
+    pub fn publish(mut self) -> PublishedPost {
+        allowed(self.post.publish());
+        PublishedPost { post: self.post }
+    }
+
+    /// Schedules the post for publication at `time`. Nothing publishes it
+    /// by itself: see `ScheduledPost::is_due`.
+    pub fn schedule_at(mut self, time: SystemTime) -> ScheduledPost {
+        allowed(self.post.schedule_at(time));
+        ScheduledPost { post: self.post }
+    }
+}
+
+impl ScheduledPost {
+    pub fn text(&self) -> &str {
+        self.post.text()
+    }
+
+    /// When the post is to be published, if it was given a time.
+    pub fn publish_at(&self) -> Option<SystemTime> {
+        self.post.publish_at()
+    }
+
+    /// Whether the post is scheduled for `now` or earlier.
+    pub fn is_due(&self, now: SystemTime) -> bool {
+        self.post.is_due(now)
+    }
+
+    pub fn publish(mut self) -> PublishedPost {
+        allowed(self.post.publish());
+        PublishedPost { post: self.post }
+    }
+}
+
+impl PublishedPost {
+    pub fn content(&self) -> &str {
+        self.post.content()
+    }
+}
+
+/// A `Post` that isn't in the state the type-state API expected, or that
+/// follows a workflow of its own. The post is handed back untouched.
+#[derive(Debug)]
+pub struct ConversionError {
+    pub post: Box<Post>,
+    pub expected: &'static [State],
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.post.has_standard_workflow() {
+            write!(f, "expected a post in state {:?}, found {:?}", self.expected, self.post.state())
+        } else {
+            write!(f, "the post doesn't follow the standard workflow")
+        }
+    }
+}
+
+impl Error for ConversionError {}
+
+fn convert(post: Post, expected: &'static [State]) -> Result<Post, ConversionError> {
+    if post.has_standard_workflow() && expected.contains(&post.state()) {
+        Ok(post)
+    } else {
+        Err(ConversionError { post: Box::new(post), expected })
+    }
+}
+
+impl TryFrom<Post> for DraftPost {
+    type Error = ConversionError;
+
+    fn try_from(post: Post) -> Result<Self, Self::Error> {
+        convert(post, &[State::Draft, State::Rejected]).map(|post| DraftPost { post })
+    }
+}
+
+impl TryFrom<Post> for PendingReviewPost {
+    type Error = ConversionError;
+
+    fn try_from(post: Post) -> Result<Self, Self::Error> {
+        convert(post, &[State::PendingReview]).map(|post| PendingReviewPost { post })
+    }
+}
+
+impl TryFrom<Post> for ApprovedPost {
+    type Error = ConversionError;
+
+    fn try_from(post: Post) -> Result<Self, Self::Error> {
+        convert(post, &[State::Approved]).map(|post| ApprovedPost { post })
+    }
+}
+
+impl TryFrom<Post> for ScheduledPost {
+    type Error = ConversionError;
+
+    fn try_from(post: Post) -> Result<Self, Self::Error> {
+        convert(post, &[State::Scheduled]).map(|post| ScheduledPost { post })
+    }
+}
+
+impl TryFrom<Post> for PublishedPost {
+    type Error = ConversionError;
+
+    fn try_from(post: Post) -> Result<Self, Self::Error> {
+        convert(post, &[State::Published]).map(|post| PublishedPost { post })
+    }
+}
+
+impl From<DraftPost> for Post {
+    fn from(post: DraftPost) -> Post {
+        post.post
+    }
+}
+
+impl From<PendingReviewPost> for Post {
+    fn from(post: PendingReviewPost) -> Post {
+        post.post
+    }
+}
+
+impl From<ApprovedPost> for Post {
+    fn from(post: ApprovedPost) -> Post {
+        post.post
+    }
+}
+
+impl From<ScheduledPost> for Post {
+    fn from(post: ScheduledPost) -> Post {
+        post.post
+    }
+}
+
+impl From<PublishedPost> for Post {
+    fn from(post: PublishedPost) -> Post {
+        post.post
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use crate::Workflow;
+
+    #[test]
+    fn round_trip() {
+        let mut draft = DraftPost::new();
+        draft.add_text("ann", "Hello");
+        let draft = draft.request_review().reject("bo", "Say more").request_review().reject("bo", "More");
+
+        let mut post = Post::from(draft);
+        assert_eq!(post.state(), State::Rejected);
+        assert_eq!(post.reviews().len(), 2);
+        post.request_review().unwrap();
+
+        let err = PublishedPost::try_from(post).unwrap_err();
+        assert_eq!(err.to_string(), "expected a post in state [Published], found PendingReview");
+        let approved = PendingReviewPost::try_from(*err.post).unwrap().approve("bo");
+        assert_eq!(approved.text(), "Hello");
+        let mut post = Post::from(approved);
+        assert_eq!(post.state(), State::Approved);
+
+        let time = SystemTime::UNIX_EPOCH;
+        post.schedule_at(time).unwrap();
+        let scheduled = ScheduledPost::try_from(post).unwrap();
+        assert_eq!(scheduled.publish_at(), Some(time));
+        assert!(scheduled.is_due(SystemTime::now()));
+        let published = scheduled.publish();
+        assert_eq!(published.content(), "Hello");
+        assert_eq!(Post::from(published).state(), State::Published);
+    }
+
+    #[test]
+    fn approved() {
+        let mut draft = DraftPost::new();
+        draft.add_text("ann", "Hello");
+        let post = Post::from(draft.request_review().approve("bo"));
+        let err = PublishedPost::try_from(post).unwrap_err();
+        assert_eq!(err.to_string(), "expected a post in state [Published], found Approved");
+        let published = ApprovedPost::try_from(*err.post).unwrap().publish();
+        assert_eq!(published.content(), "Hello");
+    }
+
+    #[test]
+    fn own_workflow() {
+        let post = Post::with_workflow(Arc::new(Workflow::standard()));
+        let err = DraftPost::try_from(post).unwrap_err();
+        assert_eq!(err.to_string(), "the post doesn't follow the standard workflow");
+    }
+}
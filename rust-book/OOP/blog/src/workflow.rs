@@ -0,0 +1,224 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Post;
+
+/// Where a post is in the editorial workflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum State {
+    Draft,
+    PendingReview,
+    /// Approved by enough reviewers, to be published now or scheduled.
+    Approved,
+    /// Sent back by a reviewer. The post can be edited and submitted again,
+    /// just like a draft: it's a state of its own only so that a post sent
+    /// back can be told from one never submitted. The reason is in the
+    /// post's reviews.
+    Rejected,
+    /// Approved, waiting for its publication time.
+    Scheduled,
+    Published,
+    Archived,
+}
+
+impl State {
+    /// Whether the content of a post in this state can change.
+    pub fn is_editable(self) -> bool {
+        matches!(self, State::Draft | State::Rejected)
+    }
+}
+
+/// Something that can happen to a post, moving it from one state to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    RequestReview,
+    Approve,
+    Reject,
+    Schedule,
+    Publish,
+    Archive,
+}
+
+/// Decides whether a transition may happen, explaining why not.
+pub type Guard = Arc<dyn Fn(&Post) -> Result<(), String> + Send + Sync>;
+/// Runs once a transition happened, with the post in its new state.
+pub type Hook = Arc<dyn Fn(&mut Post) + Send + Sync>;
+
+/// One edge of the workflow: `action` moves a post from `from` to `to`,
+/// provided every guard agrees.
+#[derive(Clone)]
+pub struct Transition {
+    pub action: Action,
+    pub from: State,
+    pub to: State,
+    guards: Vec<Guard>,
+    hooks: Vec<Hook>,
+}
+
+impl Transition {
+    pub fn new(action: Action, from: State, to: State) -> Transition {
+        Transition { action, from, to, guards: Vec::new(), hooks: Vec::new() }
+    }
+
+    pub fn guard<F>(mut self, guard: F) -> Transition
+    where
+        F: Fn(&Post) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.guards.push(Arc::new(guard));
+        self
+    }
+
+    pub fn hook<F>(mut self, hook: F) -> Transition
+    where
+        F: Fn(&mut Post) + Send + Sync + 'static,
+    {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+}
+
+impl fmt::Debug for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transition")
+            .field("action", &self.action)
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .field("guards", &self.guards.len())
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+/// Why a post could not take an action.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionError {
+    /// The workflow has no such transition out of the post's state.
+    NotAllowed { action: Action, from: State },
+    /// The transition exists but a guard refused it.
+    Refused { action: Action, from: State, reason: String },
+}
+
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransitionError::NotAllowed { action, from } => {
+                write!(f, "can't {:?} a post in state {:?}", action, from)
+            }
+            TransitionError::Refused { action, from, reason } => {
+                write!(f, "can't {:?} a post in state {:?}: {}", action, from, reason)
+            }
+        }
+    }
+}
+
+impl Error for TransitionError {}
+
+/// The states a post goes through and the transitions between them,
+/// declared as data rather than as code in each state.
+#[derive(Debug, Clone)]
+pub struct Workflow {
+    initial: State,
+    transitions: Vec<Transition>,
+    quorum: usize,
+    /// Whether the transitions are `standard`'s, which is all a repository
+    /// can store.
+    standard: bool,
+}
+
+impl Workflow {
+    /// An empty workflow: posts start in `initial` and never leave it until
+    /// transitions are added. A single approval is enough.
+    pub fn new(initial: State) -> Workflow {
+        Workflow { initial, transitions: Vec::new(), quorum: 1, standard: false }
+    }
+
+    pub fn with(mut self, transition: Transition) -> Workflow {
+        self.transitions.push(transition);
+        self.standard = false;
+        self
+    }
+
+    /// How many distinct reviewers must approve a submission before the
+    /// `Approve` transition can be taken.
+    ///
+    /// # Panics
+    ///
+    /// If `quorum` is 0.
+    pub fn with_quorum(mut self, quorum: usize) -> Workflow {
+        assert!(quorum > 0, "a quorum needs at least one approver");
+        self.quorum = quorum;
+        self
+    }
+
+    pub fn quorum(&self) -> usize {
+        self.quorum
+    }
+
+    /// Whether this is `Workflow::standard`, whatever its quorum.
+    pub fn is_standard(&self) -> bool {
+        self.standard
+    }
+
+    /// The workflow `Post::new` uses:
+    ///
+    /// - `RequestReview`: Draft or Rejected → PendingReview
+    /// - `Approve`: PendingReview → Approved
+    /// - `Reject`: PendingReview → Rejected
+    /// - `Schedule`: Approved → Scheduled
+    /// - `Publish`: Approved or Scheduled → Published
+    /// - `Archive`: Draft, Rejected or Published → Archived
+    pub fn standard() -> Workflow {
+        use Action::*;
+        use State::*;
+
+        let workflow = Workflow::new(Draft)
+            .with(Transition::new(RequestReview, Draft, PendingReview))
+            .with(Transition::new(RequestReview, Rejected, PendingReview))
+            .with(Transition::new(Approve, PendingReview, Approved))
+            .with(Transition::new(Reject, PendingReview, Rejected))
+            .with(Transition::new(Schedule, Approved, Scheduled))
+            .with(Transition::new(Publish, Approved, Published))
+            .with(Transition::new(Publish, Scheduled, Published))
+            .with(Transition::new(Archive, Draft, Archived))
+            .with(Transition::new(Archive, Rejected, Archived))
+            .with(Transition::new(Archive, Published, Archived));
+        Workflow { standard: true, ..workflow }
+    }
+
+    pub fn initial(&self) -> State {
+        self.initial
+    }
+
+    /// The transition `action` takes from `state`, if there is one.
+    pub fn transition(&self, state: State, action: Action) -> Option<&Transition> {
+        self.transitions.iter().find(|t| t.from == state && t.action == action)
+    }
+
+    /// The actions the workflow knows from `state`, guards aside.
+    pub fn actions(&self, state: State) -> Vec<Action> {
+        self.transitions.iter().filter(|t| t.from == state).map(|t| t.action).collect()
+    }
+
+    /// Moves `post` along the transition for `action`, running its hooks.
+    /// `Approve` also waits for the quorum.
+    pub(crate) fn apply(&self, post: &mut Post, action: Action) -> Result<State, TransitionError> {
+        let from = post.state();
+        let transition = self.transition(from, action)
+            .ok_or(TransitionError::NotAllowed { action, from })?;
+        if action == Action::Approve && post.approvals() < self.quorum {
+            let reason = format!("{} of {} approvals", post.approvals(), self.quorum);
+            return Err(TransitionError::Refused { action, from, reason });
+        }
+        for guard in &transition.guards {
+            guard(post).map_err(|reason| TransitionError::Refused { action, from, reason })?;
+        }
+        post.set_state(transition.to);
+        for hook in &transition.hooks {
+            hook(post);
+        }
+        Ok(transition.to)
+    }
+}
@@ -0,0 +1,6 @@
+use blog::DraftPost;
+
+fn main() {
+    let post = DraftPost::new();
+    post.approve("bo");
+}
@@ -0,0 +1,8 @@
+use blog::DraftPost;
+
+fn main() {
+    let mut post = DraftPost::new();
+    post.add_text("ann", "I ate a salad for lunch today");
+    let post = post.request_review().approve("bo");
+    println!("{}", post.content());
+}
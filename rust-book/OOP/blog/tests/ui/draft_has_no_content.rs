@@ -0,0 +1,7 @@
+use blog::DraftPost;
+
+fn main() {
+    let mut post = DraftPost::new();
+    post.add_text("ann", "I ate a salad for lunch today");
+    println!("{}", post.content());
+}
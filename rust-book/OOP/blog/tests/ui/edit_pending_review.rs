@@ -0,0 +1,6 @@
+use blog::DraftPost;
+
+fn main() {
+    let mut post = DraftPost::new().request_review();
+    post.add_text("ann", "One more thing");
+}
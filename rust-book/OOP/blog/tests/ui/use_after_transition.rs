@@ -0,0 +1,8 @@
+use blog::DraftPost;
+
+fn main() {
+    let mut post = DraftPost::new();
+    let pending = post.request_review();
+    post.add_text("ann", "Too late");
+    pending.approve("bo");
+}
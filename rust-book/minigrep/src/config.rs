@@ -0,0 +1,527 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::thread;
+
+use crate::fuzzy::FuzzyMatcher;
+use crate::multi::MultiMatcher;
+use crate::matcher::{CaseInsensitiveMatcher, LiteralMatcher, Matcher, RegexMatcher, WordMatcher};
+use crate::output::OutputOptions;
+
+pub const USAGE: &str = "\
+Usage: minigrep [OPTION]... PATTERNS [FILE]...
+Search for PATTERNS in each FILE.
+With no FILE, or when FILE is -, read standard input.
+
+Pattern selection:
+  -F, --fixed-strings        PATTERNS are plain strings (the default)
+  -E, --extended-regexp      PATTERNS are regular expressions
+  -e, --regexp=PATTERN       use PATTERN for matching; may be repeated
+  -f, --file=FILE            take PATTERNS from FILE, one per line
+  -i, --ignore-case          ignore case distinctions
+  -w, --word-regexp          only match whole words
+      --fuzzy=K              match the pattern with up to K typos (insertions,
+                             deletions or substitutions); shows the distance
+      --sort-distance        with --fuzzy, list the closest lines of each
+                             file first
+
+File selection:
+  -r, --recursive            search directories recursively
+  -z, --search-zip           search the content of gzip, bzip2, xz and zstd
+                             compressed files
+      --follow               keep reading files as they grow, like tail -F
+      --include=GLOB         only search files whose name matches GLOB
+      --exclude=GLOB         skip files whose name matches GLOB
+
+Output control:
+  -n, --line-number          print line numbers
+  -c, --count                only print a count of selected lines per file
+  -l, --files-with-matches   only print the names of files with a match
+  -v, --invert-match         select non-matching lines
+  -A, --after-context=NUM    print NUM lines of trailing context
+  -B, --before-context=NUM   print NUM lines of leading context
+  -C, --context=NUM          print NUM lines of context on both sides
+      --json                 print results as JSON Lines
+      --replace=TEMPLATE     print lines with matches replaced by TEMPLATE;
+                             with -E, $1 or ${name} insert capture groups
+      --in-place             write the replacements back to the files
+      --dry-run              with --in-place, only show the changes as a diff
+
+  -j, --threads=NUM          search NUM files at once (default: one per CPU)
+
+  -h, --help                 display this help and exit
+  -V, --version              display version information and exit
+
+Use -- to end the options, e.g. to search for a pattern starting with '-'.
+Exit status is 0 if a line is selected, 1 if none is, and 2 on error.
+";
+
+/// How the query should be interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Syntax {
+    /// `-F`: the query is a plain string (the default).
+    #[default]
+    Fixed,
+    /// `-E`: the query is a regular expression.
+    Regex,
+}
+
+/// Why the command line could not be turned into a `Config`.
+///
+/// `Help` and `Version` are not really failures: they stop the parsing
+/// because the user asked for something other than a search.
+#[derive(Debug)]
+pub enum ConfigError {
+    Help,
+    Version,
+    MissingQuery,
+    UnknownOption(String),
+    /// The option needs a value but none was given.
+    MissingValue(String),
+    /// The option doesn't take a value but got one, as in `--count=3`.
+    UnexpectedValue(String),
+    InvalidValue { option: String, value: String },
+    /// The option only makes sense along with another one.
+    Requires { option: &'static str, requires: &'static str },
+    /// The two options can't be used together.
+    Conflict { option: &'static str, with: &'static str },
+    /// The file given to `-f` could not be read.
+    PatternFile { path: String, source: io::Error },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Help => write!(f, "help requested"),
+            ConfigError::Version => write!(f, "version requested"),
+            ConfigError::MissingQuery => write!(f, "Didn't get a query string"),
+            ConfigError::UnknownOption(option) => write!(f, "unknown option '{}'", option),
+            ConfigError::MissingValue(option) => write!(f, "option '{}' requires an argument", option),
+            ConfigError::UnexpectedValue(option) => write!(f, "option '{}' doesn't allow an argument", option),
+            ConfigError::InvalidValue { option, value } => {
+                write!(f, "invalid argument '{}' for '{}'", value, option)
+            }
+            ConfigError::Requires { option, requires } => {
+                write!(f, "option '{}' requires '{}'", option, requires)
+            }
+            ConfigError::Conflict { option, with } => {
+                write!(f, "option '{}' can't be used with '{}'", option, with)
+            }
+            ConfigError::PatternFile { path, source } => write!(f, "{}: {}", path, source),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::PatternFile { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    /// A line is selected when any of these match.
+    pub patterns: Vec<String>,
+    pub paths: Vec<String>,
+    pub syntax: Syntax,
+    pub ignore_case: bool,
+    pub whole_word: bool,
+    pub recursive: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub output: OutputOptions,
+    /// `--in-place`: rewrite files with `output.replace` applied instead of
+    /// printing the results.
+    pub in_place: bool,
+    /// `--dry-run`: print the changes `--in-place` would make as a diff.
+    pub dry_run: bool,
+    /// `--fuzzy`: the most edits a match may be away from the query.
+    pub fuzzy: Option<usize>,
+    /// `-z`: decompress compressed files before searching them.
+    pub search_zip: bool,
+    /// `--follow`: keep searching what gets appended to the files.
+    pub follow: bool,
+    /// `-j`: how many files to search at once. 0 picks one thread per CPU.
+    pub threads: usize,
+}
+
+/// Every option we know, as `(short, long, takes a value)`.
+const OPTIONS: &[(Option<char>, &str, bool)] = &[
+    (Some('F'), "fixed-strings", false),
+    (Some('E'), "extended-regexp", false),
+    (Some('e'), "regexp", true),
+    (Some('f'), "file", true),
+    (Some('i'), "ignore-case", false),
+    (Some('w'), "word-regexp", false),
+    (None, "fuzzy", true),
+    (None, "sort-distance", false),
+    (Some('r'), "recursive", false),
+    (Some('z'), "search-zip", false),
+    (None, "follow", false),
+    (None, "include", true),
+    (None, "exclude", true),
+    (Some('n'), "line-number", false),
+    (Some('c'), "count", false),
+    (Some('l'), "files-with-matches", false),
+    (Some('v'), "invert-match", false),
+    (Some('A'), "after-context", true),
+    (Some('B'), "before-context", true),
+    (Some('C'), "context", true),
+    (None, "json", false),
+    (None, "replace", true),
+    (None, "in-place", false),
+    (None, "dry-run", false),
+    (Some('j'), "threads", true),
+    (Some('h'), "help", false),
+    (Some('V'), "version", false),
+];
+
+impl Config {
+    /// Parses a full command line, program name included, the way `main`
+    /// gets it from `env::args`.
+    ///
+    /// Short flags can be combined (`-inr`) and take their value either
+    /// attached (`-A3`) or as the next argument; long options take it after
+    /// `=` or as the next argument. Everything after `--` is positional.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_iter<I>(args: I) -> Result<Config, ConfigError>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+        args.next();
+
+        let mut config = Config::default();
+        let mut patterns_given = false;
+        let mut positional = Vec::new();
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                positional.extend(args.by_ref());
+                break;
+            }
+
+            if let Some(long) = arg.strip_prefix("--") {
+                let (name, inline) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let &(_, name, takes_value) = OPTIONS.iter()
+                    .find(|(_, n, _)| *n == name)
+                    .ok_or_else(|| ConfigError::UnknownOption(arg.clone()))?;
+                let option = format!("--{}", name);
+                let value = match (takes_value, inline) {
+                    (true, Some(value)) => Some(value),
+                    (true, None) => Some(args.next().ok_or(ConfigError::MissingValue(option.clone()))?),
+                    (false, Some(_)) => return Err(ConfigError::UnexpectedValue(option)),
+                    (false, None) => None,
+                };
+                config.apply(name, &option, value, &mut patterns_given)?;
+            } else if arg.len() > 1 && arg.starts_with('-') {
+                let flags = &arg[1..];
+                for (i, c) in flags.char_indices() {
+                    let option = format!("-{}", c);
+                    let &(_, name, takes_value) = OPTIONS.iter()
+                        .find(|(short, _, _)| *short == Some(c))
+                        .ok_or_else(|| ConfigError::UnknownOption(option.clone()))?;
+                    if !takes_value {
+                        config.apply(name, &option, None, &mut patterns_given)?;
+                        continue;
+                    }
+                    // The value is the rest of this argument or the next one.
+                    let rest = &flags[i + c.len_utf8()..];
+                    let value = if rest.is_empty() {
+                        args.next().ok_or(ConfigError::MissingValue(option.clone()))?
+                    } else {
+                        rest.to_string()
+                    };
+                    config.apply(name, &option, Some(value), &mut patterns_given)?;
+                    break;
+                }
+            } else {
+                positional.push(arg);
+            }
+        }
+
+        if config.output.sort_by_distance && config.fuzzy.is_none() {
+            return Err(ConfigError::Requires { option: "--sort-distance", requires: "--fuzzy" });
+        }
+        if config.in_place && config.output.replace.is_none() {
+            return Err(ConfigError::Requires { option: "--in-place", requires: "--replace" });
+        }
+        if config.dry_run && !config.in_place {
+            return Err(ConfigError::Requires { option: "--dry-run", requires: "--in-place" });
+        }
+
+        if config.follow {
+            // These only print anything once a file is done, which it never is.
+            let output = &config.output;
+            let conflicts = [
+                (output.count, "--count"),
+                (output.files_with_matches, "--files-with-matches"),
+                (output.json, "--json"),
+                (output.sort_by_distance, "--sort-distance"),
+                (config.in_place, "--in-place"),
+                (config.search_zip, "--search-zip"),
+            ];
+            if let Some(&(_, with)) = conflicts.iter().find(|(set, _)| *set) {
+                return Err(ConfigError::Conflict { option: "--follow", with });
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        if !patterns_given {
+            config.patterns.push(positional.next().ok_or(ConfigError::MissingQuery)?);
+        }
+        config.paths = positional.collect();
+        if config.paths.is_empty() {
+            // Like grep: search the current directory with -r, stdin otherwise.
+            let default = if config.recursive { "." } else { crate::walk::STDIN };
+            config.paths.push(default.to_string());
+        }
+
+        Ok(config)
+    }
+
+    fn apply(
+        &mut self,
+        name: &str,
+        option: &str,
+        value: Option<String>,
+        patterns_given: &mut bool,
+    ) -> Result<(), ConfigError> {
+        let value = value.unwrap_or_default();
+        let number = |value: &str| {
+            value.parse().map_err(|_| ConfigError::InvalidValue {
+                option: option.to_string(),
+                value: value.to_string(),
+            })
+        };
+
+        match name {
+            "fixed-strings" => self.syntax = Syntax::Fixed,
+            "extended-regexp" => self.syntax = Syntax::Regex,
+            "regexp" => {
+                self.patterns.push(value);
+                *patterns_given = true;
+            }
+            "file" => {
+                let content = fs::read_to_string(&value)
+                    .map_err(|source| ConfigError::PatternFile { path: value, source })?;
+                self.patterns.extend(content.lines().map(String::from));
+                *patterns_given = true;
+            }
+            "ignore-case" => self.ignore_case = true,
+            "word-regexp" => self.whole_word = true,
+            "fuzzy" => self.fuzzy = Some(number(&value)?),
+            "sort-distance" => self.output.sort_by_distance = true,
+            "recursive" => self.recursive = true,
+            "search-zip" => self.search_zip = true,
+            "follow" => self.follow = true,
+            "include" => self.include.push(value),
+            "exclude" => self.exclude.push(value),
+            "line-number" => self.output.line_numbers = true,
+            "count" => self.output.count = true,
+            "files-with-matches" => self.output.files_with_matches = true,
+            "invert-match" => self.output.invert = true,
+            "after-context" => self.output.after_context = number(&value)?,
+            "before-context" => self.output.before_context = number(&value)?,
+            "context" => {
+                let context = number(&value)?;
+                self.output.before_context = context;
+                self.output.after_context = context;
+            }
+            "json" => self.output.json = true,
+            "replace" => self.output.replace = Some(value),
+            "in-place" => self.in_place = true,
+            "dry-run" => self.dry_run = true,
+            "threads" => {
+                self.threads = number(&value)?;
+                if self.threads == 0 {
+                    return Err(ConfigError::InvalidValue { option: option.to_string(), value });
+                }
+            }
+            "help" => return Err(ConfigError::Help),
+            "version" => return Err(ConfigError::Version),
+            _ => unreachable!("option --{} is in OPTIONS but not handled", name),
+        }
+        Ok(())
+    }
+
+    /// How many files to search at once, resolving the default.
+    pub fn threads(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
+
+    /// Builds the matcher selected by the flags. Fails if a pattern is not a
+    /// valid regular expression.
+    pub fn matcher(&self) -> Result<Box<dyn Matcher>, Box<dyn Error>> {
+        if let Some(max_distance) = self.fuzzy {
+            let query = match &self.patterns[..] {
+                [query] => query,
+                _ => return Err("--fuzzy takes a single pattern".into()),
+            };
+            let matcher = Box::new(FuzzyMatcher::new(query, max_distance, self.ignore_case)?);
+            return Ok(if self.whole_word { Box::new(WordMatcher::new(matcher)) } else { matcher });
+        }
+
+        if self.syntax == Syntax::Regex {
+            // Several patterns become one alternation. With none at all (an
+            // empty `-f` file) nothing may match, hence a class that can't.
+            let pattern = match &self.patterns[..] {
+                [] => String::from(r"[^\s\S]"),
+                [pattern] => pattern.clone(),
+                patterns => patterns.iter().map(|p| format!("(?:{})", p)).collect::<Vec<_>>().join("|"),
+            };
+            return Ok(Box::new(RegexMatcher::new(&pattern, self.ignore_case, self.whole_word)?));
+        }
+
+        let matcher: Box<dyn Matcher> = match &self.patterns[..] {
+            [query] if self.ignore_case => Box::new(CaseInsensitiveMatcher::new(query)),
+            [query] => Box::new(LiteralMatcher::new(query)),
+            patterns => Box::new(MultiMatcher::new(patterns, self.ignore_case)),
+        };
+
+        if self.whole_word {
+            Ok(Box::new(WordMatcher::new(matcher)))
+        } else {
+            Ok(matcher)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, ConfigError> {
+        Config::from_iter(std::iter::once("minigrep").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn positional() {
+        let config = parse(&["needle", "a.txt", "b.txt"]).unwrap();
+        assert_eq!(config.patterns, ["needle"]);
+        assert_eq!(config.paths, ["a.txt", "b.txt"]);
+        assert_eq!(config.syntax, Syntax::Fixed);
+        assert!(!config.ignore_case);
+    }
+
+    #[test]
+    fn default_paths() {
+        assert_eq!(parse(&["needle"]).unwrap().paths, ["-"]);
+        assert_eq!(parse(&["-r", "needle"]).unwrap().paths, ["."]);
+    }
+
+    #[test]
+    fn combined_short_flags() {
+        let config = parse(&["-inrA2", "-B", "1", "needle", "dir"]).unwrap();
+        assert!(config.ignore_case && config.output.line_numbers && config.recursive);
+        assert_eq!(config.output.after_context, 2);
+        assert_eq!(config.output.before_context, 1);
+
+        let config = parse(&["-C3", "needle", "a"]).unwrap();
+        assert_eq!((config.output.before_context, config.output.after_context), (3, 3));
+
+        let config = parse(&["-zcj4", "needle", "a"]).unwrap();
+        assert!(config.search_zip);
+        assert!(config.output.count);
+        assert_eq!(config.threads(), 4);
+    }
+
+    #[test]
+    fn long_options() {
+        let config = parse(&["--ignore-case", "--context=2", "--include", "*.rs", "--exclude=*.md", "x", "."]).unwrap();
+        assert!(config.ignore_case);
+        assert_eq!(config.output.after_context, 2);
+        assert_eq!(config.include, ["*.rs"]);
+        assert_eq!(config.exclude, ["*.md"]);
+    }
+
+    #[test]
+    fn replace() {
+        let config = parse(&["--replace", "$1", "--in-place", "--dry-run", "-E", "(a)", "f"]).unwrap();
+        assert_eq!(config.output.replace.as_deref(), Some("$1"));
+        assert!(config.in_place && config.dry_run);
+    }
+
+    #[test]
+    fn fuzzy() {
+        let config = parse(&["--fuzzy=1", "--sort-distance", "fish", "poem.txt"]).unwrap();
+        assert_eq!(config.fuzzy, Some(1));
+        assert!(config.output.sort_by_distance);
+        assert_eq!(config.matcher().unwrap().distance("a dish"), Some(1));
+
+        assert!(matches!(parse(&["--sort-distance", "a", "b"]), Err(ConfigError::Requires { .. })));
+        assert!(parse(&["--fuzzy", "1", "-e", "a", "-e", "b", "c"]).unwrap().matcher().is_err());
+    }
+
+    #[test]
+    fn terminator() {
+        let config = parse(&["-n", "--", "-v", "-file"]).unwrap();
+        assert_eq!(config.patterns, ["-v"]);
+        assert_eq!(config.paths, ["-file"]);
+        assert!(!config.output.invert);
+    }
+
+    #[test]
+    fn patterns_from_options() {
+        let config = parse(&["-e", "one", "-etwo", "--regexp=-three", "a.txt"]).unwrap();
+        assert_eq!(config.patterns, ["one", "two", "-three"]);
+        assert_eq!(config.paths, ["a.txt"]);
+
+        let path = std::env::temp_dir().join(format!("minigrep-patterns-{}", std::process::id()));
+        fs::write(&path, "fish\ncat\n").unwrap();
+        let config = parse(&["-f", path.to_str().unwrap(), "-e", "dog", "poem.txt"]).unwrap();
+        assert_eq!(config.patterns, ["fish", "cat", "dog"]);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(parse(&["-f", "/no/such/patterns", "a"]), Err(ConfigError::PatternFile { .. })));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(parse(&[]), Err(ConfigError::MissingQuery)));
+        assert!(matches!(parse(&["-x", "needle", "a"]), Err(ConfigError::UnknownOption(o)) if o == "-x"));
+        assert!(matches!(parse(&["--nope", "needle", "a"]), Err(ConfigError::UnknownOption(o)) if o == "--nope"));
+        assert!(matches!(parse(&["needle", "a", "-A"]), Err(ConfigError::MissingValue(o)) if o == "-A"));
+        assert!(matches!(parse(&["--count=3", "needle", "a"]), Err(ConfigError::UnexpectedValue(_))));
+        assert!(matches!(
+            parse(&["-A", "many", "needle", "a"]),
+            Err(ConfigError::InvalidValue { option, value }) if option == "-A" && value == "many"
+        ));
+        assert!(matches!(parse(&["--in-place", "a", "b"]), Err(ConfigError::Requires { .. })));
+        assert!(matches!(parse(&["--replace=x", "--dry-run", "a", "b"]), Err(ConfigError::Requires { .. })));
+        assert!(matches!(parse(&["--follow", "-c", "a", "b"]), Err(ConfigError::Conflict { .. })));
+        assert!(matches!(parse(&["-j0", "needle", "a"]), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(parse(&["-h"]), Err(ConfigError::Help)));
+        assert!(matches!(parse(&["needle", "--version"]), Err(ConfigError::Version)));
+    }
+
+    #[test]
+    fn several_patterns() {
+        let config = parse(&["-e", "a.c", "-e", "dog", "x"]).unwrap();
+        let matcher = config.matcher().unwrap();
+        assert!(matcher.is_match("hot dog"));
+        assert!(matcher.is_match("a.c"));
+        assert!(!matcher.is_match("abc"));
+
+        assert_eq!(matcher.pattern_at("hot dog", &(4..7)), Some(1));
+
+        let config = Config { syntax: Syntax::Regex, ..config };
+        assert!(config.matcher().unwrap().is_match("abc"));
+
+        for syntax in [Syntax::Fixed, Syntax::Regex] {
+            let config = Config { patterns: Vec::new(), syntax, ..Config::default() };
+            assert!(!config.matcher().unwrap().is_match(""));
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::io::{self, BufRead};
+
+/// A compression format `-z` recognizes by the first bytes of a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Format {
+    pub fn detect(header: &[u8]) -> Option<Format> {
+        const MAGIC: &[(&[u8], Format)] = &[
+            (&[0x1f, 0x8b], Format::Gzip),
+            (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], Format::Xz),
+            (&[0x28, 0xb5, 0x2f, 0xfd], Format::Zstd),
+        ];
+        if is_bzip2(header) {
+            return Some(Format::Bzip2);
+        }
+        MAGIC.iter()
+            .find(|(magic, _)| header.starts_with(magic))
+            .map(|&(_, format)| format)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Gzip => "gzip",
+            Format::Bzip2 => "bzip2",
+            Format::Xz => "xz",
+            Format::Zstd => "zstd",
+        }
+    }
+}
+
+/// "BZh" alone is too likely at the start of a text file: a bzip2 stream
+/// goes on with the block size, a digit, then the magic number of either a
+/// block (the digits of pi) or the end of an empty stream (of sqrt(pi)).
+fn is_bzip2(header: &[u8]) -> bool {
+    match header {
+        [b'B', b'Z', b'h', b'1'..=b'9', rest @ ..] => {
+            rest.starts_with(&[0x31, 0x41, 0x59, 0x26, 0x53, 0x59])
+                || rest.starts_with(&[0x17, 0x72, 0x45, 0x38, 0x50, 0x90])
+        }
+        _ => false,
+    }
+}
+
+/// Wraps `reader` in a decoder when it starts like a compressed stream, so
+/// the search reads the decompressed lines. Anything else is returned as
+/// it is.
+///
+/// Each format is behind the cargo feature of the same name (`gzip`,
+/// `bzip2`, `xz`, `zstd`); reading a format that wasn't compiled in is an
+/// error rather than a search through compressed bytes.
+pub fn decoder<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    let format = Format::detect(reader.fill_buf()?);
+    Ok(match format {
+        None => Box::new(reader),
+        #[cfg(feature = "gzip")]
+        Some(Format::Gzip) => Box::new(io::BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))),
+        #[cfg(feature = "bzip2")]
+        Some(Format::Bzip2) => Box::new(io::BufReader::new(bzip2::bufread::MultiBzDecoder::new(reader))),
+        #[cfg(feature = "xz")]
+        Some(Format::Xz) => Box::new(io::BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(reader))),
+        #[cfg(feature = "zstd")]
+        Some(Format::Zstd) => Box::new(io::BufReader::new(zstd::stream::read::Decoder::with_buffer(reader)?)),
+        #[allow(unreachable_patterns)]
+        Some(format) => {
+            let message = format!("{} support was not compiled in", format.name());
+            return Err(io::Error::new(io::ErrorKind::Unsupported, message));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Read;
+
+    const POEM: &str = include_str!("../tests/fixtures/poem.txt");
+
+    fn decompress(bytes: &[u8]) -> io::Result<String> {
+        let mut text = String::new();
+        decoder(bytes)?.read_to_string(&mut text)?;
+        Ok(text)
+    }
+
+    #[test]
+    fn detect() {
+        assert_eq!(Format::detect(include_bytes!("../tests/fixtures/poem.txt.gz")), Some(Format::Gzip));
+        assert_eq!(Format::detect(include_bytes!("../tests/fixtures/poem.txt.bz2")), Some(Format::Bzip2));
+        assert_eq!(Format::detect(include_bytes!("../tests/fixtures/poem.txt.xz")), Some(Format::Xz));
+        assert_eq!(Format::detect(include_bytes!("../tests/fixtures/poem.txt.zst")), Some(Format::Zstd));
+        assert_eq!(Format::detect(POEM.as_bytes()), None);
+        assert_eq!(Format::detect(b""), None);
+        // Text that merely starts like bzip2.
+        assert_eq!(Format::detect(b"BZh9 is not a block size\n"), None);
+        assert_eq!(Format::detect(b"BZhx1AY&SY"), None);
+        assert_eq!(Format::detect(b"BZh"), None);
+    }
+
+    #[test]
+    fn plain_text_passes_through() {
+        assert_eq!(decompress(POEM.as_bytes()).unwrap(), POEM);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        assert_eq!(decompress(include_bytes!("../tests/fixtures/poem.txt.gz")).unwrap(), POEM);
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn bzip2() {
+        assert_eq!(decompress(include_bytes!("../tests/fixtures/poem.txt.bz2")).unwrap(), POEM);
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn xz() {
+        assert_eq!(decompress(include_bytes!("../tests/fixtures/poem.txt.xz")).unwrap(), POEM);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        assert_eq!(decompress(include_bytes!("../tests/fixtures/poem.txt.zst")).unwrap(), POEM);
+    }
+
+    #[cfg(not(feature = "xz"))]
+    #[test]
+    fn missing_codec() {
+        let err = decompress(include_bytes!("../tests/fixtures/poem.txt.xz")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
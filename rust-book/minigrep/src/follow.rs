@@ -0,0 +1,269 @@
+use std::convert::Infallible;
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use crate::matcher::Matcher;
+use crate::output::{FileState, Printer};
+
+/// How long to wait for more data once every file is at its end.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// `--follow`: keeps reading files as they grow, like `tail -F`.
+///
+/// Files are polled rather than watched, which works the same everywhere,
+/// network file systems included. A file truncated in place is read again
+/// from the start; a file renamed away (log rotation) is read to its end,
+/// then the new file created at the same path is followed instead. A file
+/// that doesn't exist yet is waited for.
+pub struct Follower {
+    files: Vec<Followed>,
+}
+
+struct Followed {
+    path: PathBuf,
+    /// `None` until there is a file at `path`.
+    reader: Option<BufReader<File>>,
+    /// How far into the file we've read.
+    position: u64,
+    line_number: usize,
+    /// The end of the file when it doesn't end with a newline yet: the rest
+    /// of the line is probably on its way.
+    partial: Vec<u8>,
+    id: Option<FileId>,
+    /// The printing of the file, kept from one poll to the next so that
+    /// context carries over.
+    state: FileState,
+}
+
+/// What tells a new file at the same path from the old one.
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<FileId> {
+    None
+}
+
+impl Followed {
+    fn open(path: PathBuf) -> io::Result<Followed> {
+        let (reader, id) = match File::open(&path) {
+            Ok(file) => {
+                let id = file_id(&file.metadata()?);
+                (Some(BufReader::new(file)), id)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                eprintln!("minigrep: {}: {}; waiting for it to appear", path.display(), e);
+                (None, None)
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(Followed {
+            path,
+            reader,
+            position: 0,
+            line_number: 0,
+            partial: Vec::new(),
+            id,
+            state: FileState::default(),
+        })
+    }
+
+    /// Searches what's left of the file we have open, its last line even
+    /// if it never got its newline: we're about to leave it.
+    fn finish<W: Write>(&mut self, matcher: &dyn Matcher, printer: &mut Printer<W>) -> io::Result<usize> {
+        read_new_lines(self, matcher, printer, true)
+    }
+
+    /// Starts over from the beginning of `file`, searching it. Returns how
+    /// many lines were selected.
+    fn restart<W: Write>(
+        &mut self,
+        file: File,
+        id: Option<FileId>,
+        matcher: &dyn Matcher,
+        printer: &mut Printer<W>,
+    ) -> io::Result<usize> {
+        self.reader = Some(BufReader::new(file));
+        self.position = 0;
+        self.line_number = 0;
+        self.partial.clear();
+        self.id = id;
+        self.state = FileState::default();
+        read_new_lines(self, matcher, printer, false)
+    }
+}
+
+impl Follower {
+    /// Opens every file to follow. Their current content is searched by the
+    /// first `poll`.
+    pub fn open(paths: impl IntoIterator<Item = PathBuf>) -> io::Result<Follower> {
+        let files = paths.into_iter()
+            .map(|path| Followed::open(path.clone()).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e))))
+            .collect::<io::Result<_>>()?;
+        Ok(Follower { files })
+    }
+
+    /// Searches whatever was written to the files since the last call.
+    /// Returns how many lines were selected.
+    pub fn poll<W: Write>(&mut self, matcher: &dyn Matcher, printer: &mut Printer<W>) -> io::Result<usize> {
+        let mut selected = 0;
+        for followed in &mut self.files {
+            selected += read_new_lines(followed, matcher, printer, false)?;
+            // What is at the path now may not be what we have open.
+            if let Ok(metadata) = fs::metadata(&followed.path) {
+                let id = file_id(&metadata);
+                if followed.reader.is_none() || id != followed.id {
+                    // Created, or rotated: we just read the old file to its end.
+                    if let Ok(file) = File::open(&followed.path) {
+                        selected += followed.finish(matcher, printer)?;
+                        selected += followed.restart(file, id, matcher, printer)?;
+                    }
+                } else if metadata.len() < followed.position {
+                    // Truncated in place. What's left is gone, but not a
+                    // last line we've read without its newline.
+                    selected += followed.finish(matcher, printer)?;
+                    let reader = followed.reader.as_ref().expect("a file is open");
+                    let mut file = reader.get_ref().try_clone()?;
+                    file.seek(SeekFrom::Start(0))?;
+                    selected += followed.restart(file, id, matcher, printer)?;
+                }
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Polls the files forever, printing matches as they come. Only an
+    /// error stops it.
+    pub fn run<W: Write>(&mut self, matcher: &dyn Matcher, printer: &mut Printer<W>) -> io::Result<Infallible> {
+        loop {
+            self.poll(matcher, printer)?;
+            printer.flush()?;
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// Searches the complete lines between where we were and the end of the
+/// file, and with `last` what's left after the last newline too. Returns
+/// how many lines were selected.
+fn read_new_lines<W: Write>(
+    followed: &mut Followed,
+    matcher: &dyn Matcher,
+    printer: &mut Printer<W>,
+    last: bool,
+) -> io::Result<usize> {
+    let reader = match &mut followed.reader {
+        Some(reader) => reader,
+        None => return Ok(0),
+    };
+    let mut file = printer.resume(matcher, Some(&followed.path), std::mem::take(&mut followed.state));
+    let selected_before = file.selected();
+    loop {
+        let read = reader.read_until(b'\n', &mut followed.partial)?;
+        followed.position += read as u64;
+        // Short of a newline, we're at the end of the file.
+        let complete = followed.partial.last() == Some(&b'\n');
+        if !complete && (!last || followed.partial.is_empty()) {
+            break;
+        }
+        followed.line_number += 1;
+        crate::strip_line_ending(&mut followed.partial);
+        file.line(followed.line_number, &String::from_utf8_lossy(&followed.partial))?;
+        followed.partial.clear();
+    }
+    let selected = file.selected() - selected_before;
+    followed.state = file.pause();
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::OpenOptions;
+    use std::path::Path;
+
+    use crate::matcher::LiteralMatcher;
+    use crate::output::OutputOptions;
+
+    fn append(path: &Path, text: &str) {
+        OpenOptions::new().append(true).open(path).unwrap().write_all(text.as_bytes()).unwrap();
+    }
+
+    fn output(printer: &mut Printer<Vec<u8>>) -> String {
+        String::from_utf8(std::mem::take(printer.get_mut())).unwrap()
+    }
+
+    #[test]
+    fn follows_appends_truncation_and_rotation() {
+        let root = std::env::temp_dir().join(format!("minigrep-follow-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("app.log");
+        fs::write(&path, "ERROR one\ninfo\n").unwrap();
+
+        let matcher = LiteralMatcher::new("ERROR");
+        let mut printer = Printer::new(Vec::new(), OutputOptions { line_numbers: true, ..Default::default() });
+        let mut follower = Follower::open(vec![path.clone()]).unwrap();
+
+        assert_eq!(follower.poll(&matcher, &mut printer).unwrap(), 1);
+        assert_eq!(output(&mut printer), "1:ERROR one\n");
+        assert_eq!(follower.poll(&matcher, &mut printer).unwrap(), 0);
+
+        // A line only counts once its newline is there.
+        append(&path, "ERROR tw");
+        assert_eq!(follower.poll(&matcher, &mut printer).unwrap(), 0);
+        append(&path, "o\ninfo\n");
+        assert_eq!(follower.poll(&matcher, &mut printer).unwrap(), 1);
+        assert_eq!(output(&mut printer), "3:ERROR two\n");
+
+        // Truncated, then written again.
+        fs::write(&path, "ERROR three\n").unwrap();
+        follower.poll(&matcher, &mut printer).unwrap();
+        assert_eq!(output(&mut printer), "1:ERROR three\n");
+
+        // Rotated: the end of the old file, its unfinished last line
+        // included, then the new one.
+        append(&path, "ERROR four");
+        fs::rename(&path, root.join("app.log.1")).unwrap();
+        fs::write(&path, "ERROR five\n").unwrap();
+        follower.poll(&matcher, &mut printer).unwrap();
+        assert_eq!(output(&mut printer), "2:ERROR four\n1:ERROR five\n");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn context_across_polls_and_missing_files() {
+        let root = std::env::temp_dir().join(format!("minigrep-follow-context-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("app.log");
+
+        let matcher = LiteralMatcher::new("ERROR");
+        let options = OutputOptions { line_numbers: true, after_context: 1, ..Default::default() };
+        let mut printer = Printer::new(Vec::new(), options);
+        // Not there yet: waited for.
+        let mut follower = Follower::open(vec![path.clone()]).unwrap();
+        assert_eq!(follower.poll(&matcher, &mut printer).unwrap(), 0);
+
+        fs::write(&path, "ERROR one\n").unwrap();
+        assert_eq!(follower.poll(&matcher, &mut printer).unwrap(), 1);
+        assert_eq!(output(&mut printer), "1:ERROR one\n");
+        // The context of a match in the previous poll, with no separator.
+        append(&path, "info\nERROR two\n");
+        assert_eq!(follower.poll(&matcher, &mut printer).unwrap(), 1);
+        assert_eq!(output(&mut printer), "2-info\n3:ERROR two\n");
+        append(&path, "info\ninfo\nERROR three\n");
+        follower.poll(&matcher, &mut printer).unwrap();
+        assert_eq!(output(&mut printer), "4-info\n--\n6:ERROR three\n");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::ops::Range;
+
+use crate::matcher::{self, Matcher};
+
+/// Longest query the bit-parallel search handles: one bit per character.
+pub const MAX_QUERY_LEN: usize = 64;
+
+/// Approximate search: finds the substrings of a line within a Levenshtein
+/// distance of `max_distance` from the query.
+///
+/// Lines are scanned with Myers' bit-parallel algorithm, which keeps a whole
+/// column of the edit distance matrix in a pair of `u64`s and so costs a
+/// handful of operations per character of the line.
+pub struct FuzzyMatcher {
+    query: Vec<char>,
+    max_distance: usize,
+    ignore_case: bool,
+    /// For each character of the query, the positions where it appears.
+    peq: HashMap<char, u64>,
+}
+
+/// The closest match in a line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub distance: usize,
+    /// Byte range in the line.
+    pub span: Range<usize>,
+}
+
+impl FuzzyMatcher {
+    pub fn new(query: &str, max_distance: usize, ignore_case: bool) -> Result<FuzzyMatcher, Box<dyn Error>> {
+        let query: Vec<char> = fold(query, 0, ignore_case).into_iter().map(|(_, c)| c).collect();
+        if query.len() > MAX_QUERY_LEN {
+            return Err(format!("fuzzy queries can't be longer than {} characters", MAX_QUERY_LEN).into());
+        }
+        if query.len() <= max_distance {
+            return Err(format!(
+                "a pattern of {} characters is within {} edits of anything, even an empty line",
+                query.len(),
+                max_distance
+            )
+            .into());
+        }
+        let mut peq = HashMap::new();
+        for (i, &c) in query.iter().enumerate() {
+            *peq.entry(c).or_insert(0) |= 1 << i;
+        }
+        Ok(FuzzyMatcher { query, max_distance, ignore_case, peq })
+    }
+
+    /// Returns the match closest to the query starting at or after `start`,
+    /// the leftmost one if several are as close. Of the overlapping spans
+    /// that are as close, the longest is reported, so a noisy word is
+    /// highlighted whole.
+    pub fn find_best(&self, line: &str, start: usize) -> Option<FuzzyMatch> {
+        let chars = fold(&line[start..], start, self.ignore_case);
+        let (distance, chars_span) = self.best(&chars)?;
+        Some(FuzzyMatch { distance, span: byte_span(&chars, chars_span, line) })
+    }
+
+    /// The distance and span, in `chars`, of the best match in `chars`.
+    fn best(&self, chars: &[(usize, char)]) -> Option<(usize, Range<usize>)> {
+        let m = self.query.len();
+        let mut column = Column::new(m);
+        // Leaving out every character of the query is always possible.
+        let mut best = (m, 0);
+        let mut extending = false;
+        for (j, &(_, c)) in chars.iter().enumerate() {
+            let score = column.next(self.eq(c));
+            if score < best.0 {
+                best = (score, j + 1);
+                extending = true;
+            } else if extending && score == best.0 {
+                best.1 = j + 1;
+            } else {
+                extending = false;
+            }
+        }
+
+        // The query is longer than `max_distance`, so a match is never empty.
+        let (distance, end) = best;
+        if distance > self.max_distance {
+            return None;
+        }
+        Some((distance, self.match_start(chars, end, distance)..end))
+    }
+
+    /// The distance and span, in `chars`, of the first match in `chars`:
+    /// the one ending first, then grown for as long as it gets closer to
+    /// the query, or stays as close from the same start. Scanning stops
+    /// there, so finding every match of a line takes a single pass.
+    fn leftmost(&self, chars: &[(usize, char)]) -> Option<(usize, Range<usize>)> {
+        let mut column = Column::new(self.query.len());
+        let mut found: Option<(usize, Range<usize>)> = None;
+        for (j, &(_, c)) in chars.iter().enumerate() {
+            let (score, end) = (column.next(self.eq(c)), j + 1);
+            match &mut found {
+                None if score > self.max_distance => {}
+                Some((distance, _)) if score > *distance => break,
+                Some((distance, span)) if score == *distance => {
+                    if self.match_start(chars, end, score) != span.start {
+                        break;
+                    }
+                    span.end = end;
+                }
+                _ => found = Some((score, self.match_start(chars, end, score)..end)),
+            }
+        }
+        found
+    }
+
+    /// Where `c` appears in the query, one bit per position.
+    fn eq(&self, c: char) -> u64 {
+        self.peq.get(&c).copied().unwrap_or(0)
+    }
+
+    /// Finds where the match ending before `chars[end]` starts: the longest
+    /// suffix of `chars[..end]` that is `distance` edits from the query.
+    fn match_start(&self, chars: &[(usize, char)], end: usize, distance: usize) -> usize {
+        let m = self.query.len();
+        // Edit distances between the reversed query and the reversed text,
+        // one column per character taken back from `end`.
+        let mut column: Vec<usize> = (0..=m).collect();
+        let longest = end.min(m + self.max_distance);
+        let mut start = end;
+        for len in 1..=longest {
+            let c = chars[end - len].1;
+            let mut diagonal = column[0];
+            column[0] = len;
+            for i in 1..=m {
+                let substitution = diagonal + (self.query[m - i] != c) as usize;
+                diagonal = column[i];
+                column[i] = substitution.min(column[i] + 1).min(column[i - 1] + 1);
+            }
+            if column[m] == distance {
+                start = end - len;
+            }
+        }
+        start
+    }
+}
+
+/// The last column of the edit distance matrix between the query and the
+/// line read so far, as Myers' bit vectors: `pv`/`mv` hold its vertical
+/// deltas (+1/-1) and `score` its last row, the distance of the best
+/// substring ending at the last character. Row 0 stays 0 so that matches
+/// can start anywhere.
+struct Column {
+    pv: u64,
+    mv: u64,
+    score: usize,
+    high: u64,
+}
+
+impl Column {
+    fn new(query_len: usize) -> Column {
+        Column { pv: !0, mv: 0, score: query_len, high: 1 << (query_len - 1) }
+    }
+
+    /// Reads the next character of the line, given as the positions where
+    /// it appears in the query, and returns the new score.
+    fn next(&mut self, eq: u64) -> usize {
+        let (pv, mv) = (self.pv, self.mv);
+        let xv = eq | mv;
+        let xh = ((eq & pv).wrapping_add(pv) ^ pv) | eq;
+        let mut ph = mv | !(xh | pv);
+        let mut mh = pv & xh;
+        if ph & self.high != 0 {
+            self.score += 1;
+        } else if mh & self.high != 0 {
+            self.score -= 1;
+        }
+        ph <<= 1;
+        mh <<= 1;
+        self.pv = mh | !(xv | ph);
+        self.mv = ph & xv;
+        self.score
+    }
+}
+
+/// The characters of `text` to compare with the query, each with the
+/// offset of the character it comes from, counting from `offset`. Case
+/// folding is the full one of `-i`: a `ß` becomes two characters `ss`.
+fn fold(text: &str, offset: usize, ignore_case: bool) -> Vec<(usize, char)> {
+    let mut chars = Vec::with_capacity(text.len());
+    let mut folded = String::new();
+    for (i, c) in text.char_indices() {
+        if ignore_case {
+            folded.clear();
+            matcher::fold(c, &mut folded);
+            chars.extend(folded.chars().map(|f| (offset + i, f)));
+        } else {
+            chars.push((offset + i, c));
+        }
+    }
+    chars
+}
+
+/// Byte range in `line` of `span`, a range of `chars`. A match ending
+/// inside a folding (`s` of the `ss` from `ß`) ends before that character.
+fn byte_span(chars: &[(usize, char)], span: Range<usize>, line: &str) -> Range<usize> {
+    let byte = |i: usize| chars.get(i).map_or(line.len(), |&(b, _)| b);
+    byte(span.start)..byte(span.end)
+}
+
+impl Matcher for FuzzyMatcher {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        let chars = fold(&line[start..], start, self.ignore_case);
+        self.leftmost(&chars).map(|(_, span)| byte_span(&chars, span, line))
+    }
+
+    /// Folds the line once rather than once per match.
+    fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        let chars = fold(line, 0, self.ignore_case);
+        let mut matches = Vec::new();
+        let mut from = 0;
+        while let Some((_, span)) = self.leftmost(&chars[from..]) {
+            let span = from + span.start..from + span.end;
+            from = span.end;
+            matches.push(byte_span(&chars, span, line));
+        }
+        matches
+    }
+
+    fn distance(&self, line: &str) -> Option<usize> {
+        self.find_best(line, 0).map(|m| m.distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::matcher::WordMatcher;
+
+    fn best<'a>(query: &str, k: usize, line: &'a str) -> Option<(usize, &'a str)> {
+        let matcher = FuzzyMatcher::new(query, k, false).unwrap();
+        matcher.find_best(line, 0).map(|m| (m.distance, &line[m.span]))
+    }
+
+    #[test]
+    fn exact() {
+        assert_eq!(best("fish", 0, "one fish"), Some((0, "fish")));
+        assert_eq!(best("fish", 0, "one fsh"), None);
+    }
+
+    #[test]
+    fn edits() {
+        // Deletion, insertion and substitution.
+        assert_eq!(best("fish", 1, "one fsh"), Some((1, "fsh")));
+        assert_eq!(best("fish", 1, "one fiish"), Some((1, "fiish")));
+        assert_eq!(best("fish", 1, "one dish"), Some((1, "dish")));
+        assert_eq!(best("fish", 1, "one dosh"), None);
+        assert_eq!(best("fish", 2, "one dosh"), Some((2, "dosh")));
+
+        // OCR noise: the closest of several candidates wins.
+        assert_eq!(best("invoice", 2, "lnvoke then inv0ice"), Some((1, "inv0ice")));
+    }
+
+    #[test]
+    fn unicode_and_case() {
+        assert_eq!(best("größe", 2, "die grösse"), Some((2, "grösse")));
+        assert_eq!(best("größe", 2, "DIE GRÖSSE"), None);
+        // With -i, "ß" folds to "ss" as everywhere else.
+        let matcher = FuzzyMatcher::new("größe", 2, true).unwrap();
+        let m = matcher.find_best("DIE GRÖSSE", 0).unwrap();
+        assert_eq!((m.distance, m.span), (0, 4..11));
+        let matcher = FuzzyMatcher::new("strasse", 1, true).unwrap();
+        assert_eq!(matcher.find_all("Straße, STRASE"), [0..7, 9..15]);
+    }
+
+    #[test]
+    fn offsets() {
+        let matcher = FuzzyMatcher::new("cat", 1, false).unwrap();
+        assert_eq!(matcher.find_all("cat, cot and dog"), [0..3, 5..8]);
+        // The first match, even when a closer one follows.
+        assert_eq!(matcher.find_all("cot, cat"), [0..3, 5..8]);
+        assert_eq!(matcher.find_at("cot, cat", 0), Some(0..3));
+        assert_eq!(matcher.find_at("cot, cat", 1), Some(5..8));
+        // Grown while closer, but not into the next match.
+        assert_eq!(matcher.find_at("cats", 0), Some(0..3));
+        assert_eq!(matcher.distance("a cut"), Some(1));
+        assert_eq!(matcher.distance("a dog"), None);
+
+        let matcher = FuzzyMatcher::new("aaa", 1, false).unwrap();
+        assert_eq!(matcher.find_all("aaaaaa"), [0..3, 3..6]);
+        let matcher = FuzzyMatcher::new("fish", 1, false).unwrap();
+        assert_eq!(matcher.find_all("one fiish, two fsh"), [4..9, 15..18]);
+    }
+
+    #[test]
+    fn whole_words() {
+        let matcher = WordMatcher::new(Box::new(FuzzyMatcher::new("cat", 1, false).unwrap()));
+        assert!(matcher.is_match("cot scatter"));
+        assert_eq!(matcher.find_all("cot scatter, cut"), [0..3, 13..16]);
+        assert!(!matcher.is_match("scatter"));
+    }
+
+    #[test]
+    fn too_many_edits() {
+        // Within 3 edits of "cat" is every line, so that's an error.
+        assert!(FuzzyMatcher::new("cat", 3, false).is_err());
+        assert!(FuzzyMatcher::new("", 0, false).is_err());
+        assert!(FuzzyMatcher::new("cat", 2, false).unwrap().find_best("", 0).is_none());
+    }
+
+    #[test]
+    fn long_queries() {
+        assert!(FuzzyMatcher::new(&"a".repeat(64), 1, false).is_ok());
+        assert!(FuzzyMatcher::new(&"a".repeat(65), 1, false).is_err());
+        let query = "x".repeat(64);
+        let line = format!("--{}y{}--", &query[..30], &query[30..]);
+        assert_eq!(FuzzyMatcher::new(&query, 1, false).unwrap().distance(&line), Some(1));
+    }
+}
@@ -1,64 +1,240 @@
-use std::env;
-use std::fs;
 use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-pub struct Config {
-    pub query: String,
-    pub filename: String,
-    pub case_sensitive: bool,
+pub mod config;
+pub mod decompress;
+pub mod follow;
+pub mod fuzzy;
+pub mod matcher;
+pub mod multi;
+pub mod output;
+pub mod parallel;
+pub mod replace;
+pub mod walk;
+
+pub use config::{Config, ConfigError, Syntax};
+
+use matcher::{CaseInsensitiveMatcher, LiteralMatcher, Matcher};
+use multi::MultiMatcher;
+use output::Printer;
+use walk::FileFilter;
+
+/// Files are read in chunks this big; lines are split out of each chunk.
+const READ_BUFFER_LEN: usize = 64 * 1024;
+
+/// Searches every path of `config`, printing the results to stdout.
+///
+/// Returns whether any line was selected, which decides the exit status.
+pub fn run(config: Config) -> Result<bool, Box<dyn Error>> {
+    let matcher = config.matcher()?;
+    let filter = FileFilter::new(&config.include, &config.exclude)?;
+    let files = walk::files(&config.paths, config.recursive, &filter);
+
+    let stdout = io::stdout();
+    if let (true, Some(template)) = (config.in_place, &config.output.replace) {
+        let mut out = BufWriter::new(stdout.lock());
+        let (changed, failures) = replace::edit_files(&*matcher, template, files, config.dry_run, &mut out)?;
+        out.flush()?;
+        return finish(changed, failures);
+    }
+
+    let mut options = config.output.clone();
+    // Like grep, name the file on every line as soon as there may be several.
+    options.with_filename = config.paths.len() > 1
+        || (config.recursive && config.paths.iter().any(|p| Path::new(p).is_dir()));
+    options.color = stdout.is_terminal() && !options.json;
+    // Stdout is line buffered itself, which is what a terminal wants; a
+    // pipe is better served by bigger writes.
+    let out: Box<dyn Write> = if stdout.is_terminal() {
+        Box::new(stdout.lock())
+    } else {
+        Box::new(BufWriter::new(stdout.lock()))
+    };
+    let mut printer = Printer::new(out, options);
+
+    if config.follow {
+        return follow_files(&*matcher, files, &mut printer);
+    }
+
+    // Threads only pay off with several files. Stdin is searched on this
+    // thread, so that its lines come out as they are read.
+    let single = config.paths.len() == 1 && !(config.recursive && Path::new(&config.paths[0]).is_dir());
+    let stdin = config.paths.iter().any(|p| p == walk::STDIN);
+    let threads = config.threads();
+    let (selected, failures) = if threads > 1 && !single && !stdin {
+        parallel::search_files(&*matcher, files, threads, config.search_zip, &mut printer)?
+    } else {
+        let (mut selected, mut failures) = (0, 0);
+        for path in files {
+            // A file we can't read shouldn't stop us from searching the others.
+            match path.and_then(|path| search_file(&*matcher, &path, config.search_zip, &mut printer)) {
+                Ok(count) => selected += count,
+                Err(e) => {
+                    eprintln!("minigrep: {}", e);
+                    failures += 1;
+                }
+            }
+        }
+        (selected, failures)
+    };
+    printer.summary()?;
+    printer.into_inner().flush()?;
+    finish(selected, failures)
+}
+
+/// `--follow`: searches stdin as usual, then follows the files until killed.
+fn follow_files<W: Write>(
+    matcher: &dyn Matcher,
+    files: impl Iterator<Item = Result<PathBuf, Box<dyn Error>>>,
+    printer: &mut Printer<W>,
+) -> Result<bool, Box<dyn Error>> {
+    let mut paths = Vec::new();
+    for path in files {
+        let path = path?;
+        if path == Path::new(walk::STDIN) {
+            // A pipe already delivers lines as they come.
+            search_file(matcher, &path, false, printer)?;
+            printer.flush()?;
+        } else {
+            paths.push(path);
+        }
+    }
+    if paths.is_empty() {
+        return Ok(printer.stats().matched_lines > 0);
+    }
+    match follow::Follower::open(paths)?.run(matcher, printer) {
+        Ok(never) => match never {},
+        Err(e) => Err(e.into()),
+    }
 }
 
-impl Config {
-    pub fn new(mut args: env::Args) -> Result<Config, &'static str> {
-        args.next();
+fn finish(selected: usize, failures: usize) -> Result<bool, Box<dyn Error>> {
+    if failures > 0 {
+        return Err(format!("{} path(s) could not be searched", failures).into());
+    }
+    Ok(selected > 0)
+}
 
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a query string"),
-        };
-        let filename = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a filename"),
+/// Returns how many lines of the file were selected. `-` is stdin. With
+/// `search_zip`, compressed input is searched decompressed.
+pub(crate) fn search_file<W: Write>(
+    matcher: &dyn Matcher,
+    path: &Path,
+    search_zip: bool,
+    printer: &mut Printer<W>,
+) -> Result<usize, Box<dyn Error>> {
+    if path == Path::new(walk::STDIN) {
+        let stdin = io::stdin();
+        let reader: Box<dyn BufRead> = if search_zip {
+            decompress::decoder(stdin.lock())?
+        } else {
+            Box::new(stdin.lock())
         };
+        return Ok(search_reader(matcher, reader, None, printer)?);
+    }
 
-        let case_sensitive = env::var("CASE_INSENSITIVE").is_err();
-
-        Ok(Config {query, filename, case_sensitive})
+    let with_path = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let file = File::open(path).map_err(|e| with_path(&e))?;
+    let mut reader: Box<dyn BufRead> = Box::new(BufReader::with_capacity(READ_BUFFER_LEN, file));
+    if search_zip {
+        reader = decompress::decoder(reader).map_err(|e| with_path(&e))?;
     }
+    Ok(search_reader(matcher, reader, Some(path), printer).map_err(|e| with_path(&e))?)
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let content = fs::read_to_string(config.filename)?;
+/// Searches `reader` line by line, so only the current line (plus any
+/// `-B` context) is ever held in memory, whatever the size of the input.
+///
+/// Lines that aren't valid UTF-8 are searched and printed with the invalid
+/// bytes replaced by U+FFFD. Input that looks binary is skipped entirely.
+/// Returns how many lines were selected.
+pub fn search_reader<R: BufRead, W: Write>(
+    matcher: &dyn Matcher,
+    mut reader: R,
+    path: Option<&Path>,
+    printer: &mut Printer<W>,
+) -> io::Result<usize> {
+    if walk::is_binary(reader.fill_buf()?) {
+        return Ok(0);
+    }
 
-    let results = if config.case_sensitive {
-        search(&config.query, &content)
+    let mut file = printer.file(matcher, path);
+    let mut buf = Vec::new();
+    let mut number = 0;
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        number += 1;
+        strip_line_ending(&mut buf);
+        if !file.line(number, &String::from_utf8_lossy(&buf))? {
+            break;
+        }
     }
-    else {
-        search_case_insensitive(&config.query, &content)
-    };
+    file.finish()
+}
 
-    for line in results {
-        println!("{}", line);
+/// Strips the terminator the same way `str::lines` does.
+pub(crate) fn strip_line_ending(line: &mut Vec<u8>) {
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
     }
-    Ok(())
 }
 
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+/// A line selected by a search, along with where the query matched in it.
+#[derive(Debug, PartialEq)]
+pub struct Match<'a> {
+    /// Starts at 1, like the `-n` output.
+    pub line_number: usize,
+    pub line: &'a str,
+    /// Byte ranges of every match within `line`.
+    pub spans: Vec<Range<usize>>,
+}
+
+pub fn search_with<'a>(matcher: &dyn Matcher, contents: &'a str) -> Vec<Match<'a>> {
     contents.lines()
-            .filter(|l| l.contains(query))
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let spans = matcher.find_all(line);
+                if spans.is_empty() {
+                    None
+                } else {
+                    Some(Match { line_number: i + 1, line, spans })
+                }
+            })
             .collect()
 }
 
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    contents.lines()
-        .filter(|l| l.to_lowercase().contains(&query.to_lowercase()))
-        .collect()
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    search_with(&LiteralMatcher::new(query), contents)
+}
+
+/// Lines matching any of `queries`. A single query is looked for on its
+/// own, more of them all at once with an Aho–Corasick automaton.
+pub fn search_any<'a, S: AsRef<str>>(queries: &[S], contents: &'a str) -> Vec<Match<'a>> {
+    match queries {
+        [query] => search(query.as_ref(), contents),
+        _ => search_with(&MultiMatcher::new(queries, false), contents),
+    }
+}
+
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    search_with(&CaseInsensitiveMatcher::new(query), contents)
 }
 
 
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod tests {
     use super::*;
+    use output::OutputOptions;
 
     #[test]
     fn one_result() {
@@ -69,7 +245,7 @@ safe, fast, productive.
 Pick three.";
 
         assert_eq!(
-            vec!["safe, fast, productive."],
+            vec![Match { line_number: 2, line: "safe, fast, productive.", spans: vec![15..19] }],
             search(query, contents)
         );
     }
@@ -83,9 +259,82 @@ safe, fast, productive.
 Pick three.
 Trust me.";
 
+        let results = search_case_insensitive(query, contents);
         assert_eq!(
             vec!["Rust:", "Trust me."],
-            search_case_insensitive(query, contents)
+            results.iter().map(|m| m.line).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![1, 4], results.iter().map(|m| m.line_number).collect::<Vec<_>>());
+        assert_eq!(vec![0..4], results[0].spans);
+        assert_eq!(vec![1..5], results[1].spans);
+    }
+
+    #[test]
+    fn whole_word_regex() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+        let config = Config {
+            patterns: vec![String::from("[a-z]ust")],
+            syntax: Syntax::Regex,
+            ignore_case: true,
+            whole_word: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            vec![Match { line_number: 1, line: "Rust:", spans: vec![0..4] }],
+            search_with(&*config.matcher().unwrap(), contents)
+        );
+    }
+
+    #[test]
+    fn streaming() {
+        let matcher = LiteralMatcher::new("fish");
+        let mut printer = Printer::new(Vec::new(), OutputOptions { line_numbers: true, ..Default::default() });
+        // CRLF endings, an invalid byte and no final newline.
+        let input: &[u8] = b"one fish\r\ntwo cats\r\nred \xff fish";
+        let selected = search_reader(&matcher, input, None, &mut printer).unwrap();
+        assert_eq!(selected, 2);
+        assert_eq!(
+            String::from_utf8(printer.into_inner()).unwrap(),
+            "1:one fish\n3:red \u{fffd} fish\n"
+        );
+
+        let mut printer = Printer::new(Vec::new(), OutputOptions::default());
+        let binary: &[u8] = b"fish\0fish\n";
+        assert_eq!(search_reader(&matcher, binary, None, &mut printer).unwrap(), 0);
+        assert!(printer.into_inner().is_empty());
+    }
+
+    #[test]
+    fn case_insensitive_unicode() {
+        let contents = "\
+Größe: 12
+GRÖSSE: 14
+Grösse: 16";
+
+        let results = search_case_insensitive("größe", contents);
+        let found: Vec<_> = results.iter().map(|m| &m.line[m.spans[0].clone()]).collect();
+        assert_eq!(vec!["Größe", "GRÖSSE", "Grösse"], found);
+    }
+
+    #[test]
+    fn several_queries() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.";
+
+        let results = search_any(&["three", "fast", "safe"], contents);
+        assert_eq!(
+            vec![
+                Match { line_number: 2, line: "safe, fast, productive.", spans: vec![0..4, 6..10] },
+                Match { line_number: 3, line: "Pick three.", spans: vec![5..10] },
+            ],
+            results
         );
     }
 }
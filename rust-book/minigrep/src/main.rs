@@ -1,17 +1,35 @@
 use std::env;
 use std::process;
 
-use minigrep::Config;
+use minigrep::{Config, ConfigError};
 
 fn main() {
 
-    let config = Config::new(env::args()).unwrap_or_else( |err| {
-        eprintln!("Problem parsing the arguments: {}", err);
-        process::exit(1);
+    let config = Config::from_iter(env::args()).unwrap_or_else( |err| {
+        match err {
+            ConfigError::Help => {
+                print!("{}", minigrep::config::USAGE);
+                process::exit(0);
+            }
+            ConfigError::Version => {
+                println!("minigrep {}", env!("CARGO_PKG_VERSION"));
+                process::exit(0);
+            }
+            err => {
+                eprintln!("Problem parsing the arguments: {}", err);
+                eprintln!("Try 'minigrep --help' for more information.");
+                process::exit(2);
+            }
+        }
     });
 
-    if let Err(e) = minigrep::run(config) {
-        eprintln!("Application error: {}", e);
-        process::exit(1);
+    // Like grep: 0 when something was selected, 1 when nothing was, 2 on error.
+    match minigrep::run(config) {
+        Ok(true) => process::exit(0),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("Application error: {}", e);
+            process::exit(2);
+        }
     }
 }
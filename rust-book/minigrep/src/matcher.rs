@@ -0,0 +1,360 @@
+use std::error::Error;
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+/// Finds occurrences of the query in a single line.
+///
+/// Matchers are built once from the `Config` and then reused for every line,
+/// so any per-query work (lowercasing, compiling a regex) happens up front.
+/// They are shared between the threads searching files in parallel.
+pub trait Matcher: Send + Sync {
+    /// Returns the byte range of the first match starting at or after `start`.
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>>;
+
+    fn is_match(&self, line: &str) -> bool {
+        self.find_at(line, 0).is_some()
+    }
+
+    /// Returns every non-overlapping match in `line`, from left to right.
+    fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        let mut matches = Vec::new();
+        let mut start = 0;
+        while start <= line.len() {
+            let m = match self.find_at(line, start) {
+                Some(m) => m,
+                None => break,
+            };
+            // An empty match would be found again and again, step over a char.
+            start = if m.is_empty() {
+                m.end + line[m.end..].chars().next().map_or(1, char::len_utf8)
+            } else {
+                m.end
+            };
+            matches.push(m);
+        }
+        matches
+    }
+
+    /// How far the closest match in `line` is from the query, for matchers
+    /// that don't only find exact matches.
+    fn distance(&self, _line: &str) -> Option<usize> {
+        None
+    }
+
+    /// With several patterns, which one matched at `span`, as its index in
+    /// the order they were given.
+    fn pattern_at(&self, _line: &str, _span: &Range<usize>) -> Option<usize> {
+        None
+    }
+
+    /// Replaces every match in `line` with `template`. Only regular
+    /// expressions have capture groups, so by default the template is
+    /// inserted as it is.
+    fn replace_all(&self, line: &str, template: &str) -> String {
+        let mut replaced = String::with_capacity(line.len());
+        let mut last = 0;
+        for m in self.find_all(line) {
+            replaced.push_str(&line[last..m.start]);
+            replaced.push_str(template);
+            last = m.end;
+        }
+        replaced.push_str(&line[last..]);
+        replaced
+    }
+}
+
+/// Plain substring search, what `search` has always done.
+pub struct LiteralMatcher {
+    query: String,
+}
+
+impl LiteralMatcher {
+    pub fn new(query: &str) -> LiteralMatcher {
+        LiteralMatcher { query: query.to_string() }
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        line[start..].find(&self.query)
+            .map(|i| start + i..start + i + self.query.len())
+    }
+}
+
+/// Substring search ignoring case, using full Unicode case folding: `ß`
+/// matches `SS`, `ς` matches `Σ`, `ﬁ` matches `FI` and so on. The query is
+/// folded once here rather than on every line.
+///
+/// Like Unicode's default (non-Turkic) folding, `İ` folds to `i` followed
+/// by a combining dot and `ı` only matches itself.
+pub struct CaseInsensitiveMatcher {
+    query: String,
+}
+
+impl CaseInsensitiveMatcher {
+    pub fn new(query: &str) -> CaseInsensitiveMatcher {
+        let mut folded = String::new();
+        query.chars().for_each(|c| fold(c, &mut folded));
+        CaseInsensitiveMatcher { query: folded }
+    }
+}
+
+/// Pushes the full case folding of `c` to `out`. Going lower, upper, then
+/// lower again gives the folding from Unicode's CaseFolding.txt (`ẞ` → `ß`
+/// → `SS` → `ss`) for everything but the dotless i, which uppercasing would
+/// merge with the plain one.
+pub(crate) fn fold(c: char, out: &mut String) {
+    if c.is_ascii() {
+        out.push(c.to_ascii_lowercase());
+    } else if c == '\u{131}' {
+        out.push(c);
+    } else {
+        c.to_lowercase()
+            .flat_map(char::to_uppercase)
+            .for_each(|upper| out.extend(upper.to_lowercase()));
+    }
+}
+
+/// A line after case folding, remembering which original character each of
+/// its bytes came from.
+pub(crate) struct FoldedLine {
+    pub(crate) text: String,
+    /// For each byte of `text`, the offset in the original line of the
+    /// character it was folded from.
+    origins: Vec<usize>,
+    /// The offset in the original line where folding stopped.
+    end: usize,
+}
+
+impl FoldedLine {
+    pub(crate) fn new(line: &str, start: usize) -> FoldedLine {
+        let mut text = String::with_capacity(line.len() - start);
+        let mut origins = Vec::with_capacity(line.len() - start);
+        for (i, c) in line[start..].char_indices() {
+            fold(c, &mut text);
+            origins.resize(text.len(), start + i);
+        }
+        FoldedLine { text, origins, end: line.len() }
+    }
+
+    /// Maps an offset in `text` back to the original line, if it falls
+    /// between the foldings of two characters. Matching half of a folding
+    /// (the first `s` of the `ss` from a `ß`) would split a character.
+    pub(crate) fn original(&self, offset: usize) -> Option<usize> {
+        if offset == self.text.len() {
+            Some(self.end)
+        } else if offset == 0 || self.origins[offset - 1] != self.origins[offset] {
+            Some(self.origins[offset])
+        } else {
+            None
+        }
+    }
+}
+
+impl CaseInsensitiveMatcher {
+    /// The first match at or after `from` in a folded line, as the offset
+    /// in `folded.text` where it ends and its span in the original line.
+    fn find_folded(&self, folded: &FoldedLine, mut from: usize) -> Option<(usize, Range<usize>)> {
+        while let Some(i) = folded.text[from..].find(&self.query) {
+            let (begin, end) = (from + i, from + i + self.query.len());
+            if let (Some(original_begin), Some(original_end)) = (folded.original(begin), folded.original(end)) {
+                return Some((end, original_begin..original_end));
+            }
+            from = begin + folded.text[begin..].chars().next()?.len_utf8();
+        }
+        None
+    }
+}
+
+impl Matcher for CaseInsensitiveMatcher {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        // ASCII folds byte for byte, so offsets are already the right ones.
+        if line[start..].is_ascii() {
+            return line[start..].to_ascii_lowercase().find(&self.query)
+                .map(|i| start + i..start + i + self.query.len());
+        }
+
+        let folded = FoldedLine::new(line, start);
+        self.find_folded(&folded, 0).map(|(_, span)| span)
+    }
+
+    /// Folds the line once rather than once per match.
+    fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        let folded = FoldedLine::new(line, 0);
+        let mut matches = Vec::new();
+        let mut from = 0;
+        while from <= folded.text.len() {
+            let (end, span) = match self.find_folded(&folded, from) {
+                Some(found) => found,
+                None => break,
+            };
+            // An empty match would be found again and again, step over a char.
+            from = if span.is_empty() {
+                end + folded.text[end..].chars().next().map_or(1, char::len_utf8)
+            } else {
+                end
+            };
+            matches.push(span);
+        }
+        matches
+    }
+}
+
+/// Only keeps the matches of another matcher that are a whole word, meaning
+/// they are not directly preceded or followed by a word character.
+pub struct WordMatcher {
+    inner: Box<dyn Matcher>,
+}
+
+impl WordMatcher {
+    pub fn new(inner: Box<dyn Matcher>) -> WordMatcher {
+        WordMatcher { inner }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Matcher for WordMatcher {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        let mut start = start;
+        while let Some(m) = self.inner.find_at(line, start) {
+            let before = line[..m.start].chars().next_back();
+            let after = line[m.end..].chars().next();
+            if !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char) {
+                return Some(m);
+            }
+            // Try again from the next character after the rejected match.
+            match line[m.start..].chars().next() {
+                Some(c) => start = m.start + c.len_utf8(),
+                None => return None,
+            }
+        }
+        None
+    }
+
+    fn distance(&self, line: &str) -> Option<usize> {
+        self.inner.distance(line)
+    }
+
+    fn pattern_at(&self, line: &str, span: &Range<usize>) -> Option<usize> {
+        self.inner.pattern_at(line, span)
+    }
+}
+
+/// Regular expression search, using the `regex` crate syntax.
+pub struct RegexMatcher {
+    regex: Regex,
+}
+
+impl RegexMatcher {
+    pub fn new(pattern: &str, ignore_case: bool, whole_word: bool) -> Result<RegexMatcher, Box<dyn Error>> {
+        let pattern = if whole_word {
+            format!(r"\b(?:{})\b", pattern)
+        } else {
+            pattern.to_string()
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()?;
+        Ok(RegexMatcher { regex })
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        self.regex.find_at(line, start).map(|m| m.range())
+    }
+
+    /// `$1` or `${name}` in the template stand for capture groups, `$$` for
+    /// a dollar sign.
+    fn replace_all(&self, line: &str, template: &str) -> String {
+        self.regex.replace_all(line, template).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal() {
+        let matcher = LiteralMatcher::new("ab");
+        assert_eq!(matcher.find_at("xxabab", 0), Some(2..4));
+        assert_eq!(matcher.find_at("xxabab", 3), Some(4..6));
+        assert_eq!(matcher.find_at("xxabab", 5), None);
+        assert!(!matcher.is_match("AB"));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let matcher = CaseInsensitiveMatcher::new("RuSt");
+        assert_eq!(matcher.find_at("Trust me", 0), Some(1..5));
+        assert!(matcher.is_match("RUST"));
+        assert!(!matcher.is_match("rus t"));
+    }
+
+    #[test]
+    fn case_folding() {
+        // Foldings that change the byte length keep offsets in the original.
+        let matcher = CaseInsensitiveMatcher::new("strasse");
+        assert_eq!(matcher.find_at("Die Straße", 0), Some(4..11));
+        assert_eq!(matcher.find_at("DIE STRAẞE", 0), Some(4..12));
+        assert_eq!(CaseInsensitiveMatcher::new("Maß").find_at("MASS", 0), Some(0..4));
+
+        let matcher = CaseInsensitiveMatcher::new("σίσυφος");
+        assert_eq!(matcher.find_at("Ο ΣΊΣΥΦΟΣ", 0), Some(3..17));
+
+        // The Kelvin sign and the ligature fold to plain ASCII.
+        assert_eq!(CaseInsensitiveMatcher::new("kelvin").find_at("\u{212a}ELVIN", 0), Some(0..8));
+        assert_eq!(CaseInsensitiveMatcher::new("FIND").find_at("ﬁnd", 0), Some(0..5));
+
+        // Only whole characters match: "s" is half of the folded "ß".
+        assert!(!CaseInsensitiveMatcher::new("s").is_match("ß"));
+        assert_eq!(CaseInsensitiveMatcher::new("s").find_at("ßs", 0), Some(2..3));
+
+        // Default folding, not the Turkish one.
+        let matcher = CaseInsensitiveMatcher::new("İstanbul");
+        assert!(matcher.is_match("i\u{307}STANBUL"));
+        assert!(!matcher.is_match("istanbul"));
+        assert!(!CaseInsensitiveMatcher::new("ı").is_match("I"));
+        assert_eq!(CaseInsensitiveMatcher::new("ı").find_at("Iı", 0), Some(1..3));
+
+        // Searching from an offset.
+        assert_eq!(CaseInsensitiveMatcher::new("ss").find_at("ß ß", 2), Some(3..5));
+
+        // Every match of a line, against a single folding of it.
+        let matcher = CaseInsensitiveMatcher::new("ss");
+        assert_eq!(matcher.find_all("ßSSsß ẞs"), [0..2, 2..4, 5..7, 8..11]);
+        assert_eq!(matcher.replace_all("Maße MASSE", "x"), "Maxe MAxE");
+        assert_eq!(CaseInsensitiveMatcher::new("").find_all("ßa"), [0..0, 2..2, 3..3]);
+    }
+
+    #[test]
+    fn whole_word() {
+        let matcher = WordMatcher::new(Box::new(LiteralMatcher::new("cat")));
+        assert_eq!(matcher.find_at("concatenate cat", 0), Some(12..15));
+        assert!(matcher.is_match("cat"));
+        assert!(matcher.is_match("the cat, again"));
+        assert!(!matcher.is_match("cats"));
+        assert!(!matcher.is_match("_cat"));
+        assert!(!matcher.is_match("écat"));
+    }
+
+    #[test]
+    fn regex() {
+        let matcher = RegexMatcher::new("fa[st]+", false, false).unwrap();
+        assert_eq!(matcher.find_at("safe, fast, productive.", 0), Some(6..10));
+
+        let matcher = RegexMatcher::new("RU?ST", true, false).unwrap();
+        assert!(matcher.is_match("Trust me."));
+
+        let matcher = RegexMatcher::new("ru?st", false, true).unwrap();
+        assert!(!matcher.is_match("Trust me."));
+        assert!(matcher.is_match("I rst my case"));
+
+        assert!(RegexMatcher::new("(unclosed", false, false).is_err());
+    }
+}
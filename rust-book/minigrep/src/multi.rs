@@ -0,0 +1,238 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::matcher::{self, FoldedLine, Matcher};
+
+/// Searches for many literal patterns at once with an Aho–Corasick
+/// automaton, so each line is scanned a single time however many patterns
+/// there are.
+///
+/// Like grep, the leftmost match wins, and the longest of those starting
+/// at the same place.
+pub struct MultiMatcher {
+    /// The trie of every pattern, node 0 being the root.
+    nodes: Vec<Node>,
+    ignore_case: bool,
+    /// Length in bytes of the longest pattern.
+    max_len: usize,
+}
+
+#[derive(Default)]
+struct Node {
+    /// Trie edges, sorted by byte.
+    next: Vec<(u8, usize)>,
+    /// The node for the longest proper suffix of this one's path that is
+    /// also in the trie, where the search goes on when `next` has no edge.
+    fail: usize,
+    /// The pattern spelled by the path to this node, if any.
+    pattern: Option<usize>,
+    /// The closest node along the `fail` chain that ends a pattern: those
+    /// patterns match as well whenever this node is reached.
+    output: Option<usize>,
+    /// Length of the path to this node.
+    depth: usize,
+}
+
+impl Node {
+    fn child(&self, byte: u8) -> Option<usize> {
+        self.next.binary_search_by_key(&byte, |&(b, _)| b).ok().map(|i| self.next[i].1)
+    }
+}
+
+impl MultiMatcher {
+    pub fn new<S: AsRef<str>>(patterns: &[S], ignore_case: bool) -> MultiMatcher {
+        let mut nodes = vec![Node::default()];
+        let mut max_len = 0;
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            let pattern = pattern.as_ref();
+            let folded;
+            let pattern = if ignore_case {
+                let mut out = String::new();
+                pattern.chars().for_each(|c| matcher::fold(c, &mut out));
+                folded = out;
+                &folded
+            } else {
+                pattern
+            };
+            max_len = max_len.max(pattern.len());
+
+            let mut node = 0;
+            for &byte in pattern.as_bytes() {
+                node = match nodes[node].next.binary_search_by_key(&byte, |&(b, _)| b) {
+                    Ok(i) => nodes[node].next[i].1,
+                    Err(i) => {
+                        let child = nodes.len();
+                        let depth = nodes[node].depth + 1;
+                        nodes.push(Node { depth, ..Node::default() });
+                        nodes[node].next.insert(i, (byte, child));
+                        child
+                    }
+                };
+            }
+            // The first of several identical patterns is the one reported.
+            nodes[node].pattern.get_or_insert(index);
+        }
+
+        // Breadth first, so the fail links of shallower nodes are all set
+        // by the time deeper nodes need them.
+        let mut queue: VecDeque<usize> = nodes[0].next.iter().map(|&(_, child)| child).collect();
+        while let Some(node) = queue.pop_front() {
+            for i in 0..nodes[node].next.len() {
+                let (byte, child) = nodes[node].next[i];
+                let mut fail = nodes[node].fail;
+                let fail = loop {
+                    if let Some(next) = nodes[fail].child(byte) {
+                        break next;
+                    }
+                    if fail == 0 {
+                        break 0;
+                    }
+                    fail = nodes[fail].fail;
+                };
+                nodes[child].fail = fail;
+                nodes[child].output = match nodes[fail].pattern {
+                    Some(_) if fail != 0 => Some(fail),
+                    _ => nodes[fail].output,
+                };
+                queue.push_back(child);
+            }
+        }
+
+        MultiMatcher { nodes, ignore_case, max_len }
+    }
+
+    /// Returns the index of the pattern that matched along with where,
+    /// looking at or after `start`.
+    pub fn find_pattern_at(&self, line: &str, start: usize) -> Option<(usize, Range<usize>)> {
+        if !self.ignore_case {
+            return self.scan(line, start, Some);
+        }
+
+        // Folding may change lengths: search the folded line, then map the
+        // match back, skipping those that only cover part of a character.
+        let folded = FoldedLine::new(line, start);
+        self.scan(&folded.text, 0, |span| Some(folded.original(span.start)?..folded.original(span.end)?))
+    }
+
+    fn scan(
+        &self,
+        text: &str,
+        from: usize,
+        accept: impl Fn(Range<usize>) -> Option<Range<usize>>,
+    ) -> Option<(usize, Range<usize>)> {
+        // The best match so far, as (start, end, pattern) in `text`, and the
+        // same span in the line.
+        let mut best: Option<(usize, usize, usize, Range<usize>)> = None;
+        let consider = |best: &mut Option<(usize, usize, usize, Range<usize>)>, pattern, start, end| {
+            let better = best.as_ref().is_none_or(|&(s, e, _, _)| start < s || (start == s && end > e));
+            if better {
+                if let Some(span) = accept(start..end) {
+                    *best = Some((start, end, pattern, span));
+                }
+            }
+        };
+
+        if let Some(pattern) = self.nodes[0].pattern {
+            consider(&mut best, pattern, from, from);
+        }
+        let mut state = 0;
+        for (i, &byte) in text.as_bytes()[from..].iter().enumerate() {
+            let end = from + i + 1;
+            // Nothing ending here or later can start before the best match.
+            if best.as_ref().is_some_and(|&(start, _, _, _)| end > start + self.max_len) {
+                break;
+            }
+            state = self.step(state, byte);
+
+            let mut out = if self.nodes[state].pattern.is_some() { Some(state) } else { self.nodes[state].output };
+            while let Some(node) = out {
+                let node_ref = &self.nodes[node];
+                if let Some(pattern) = node_ref.pattern {
+                    consider(&mut best, pattern, end - node_ref.depth, end);
+                }
+                out = node_ref.output;
+            }
+        }
+        best.map(|(_, _, pattern, span)| (pattern, span))
+    }
+
+    fn step(&self, mut state: usize, byte: u8) -> usize {
+        loop {
+            if let Some(next) = self.nodes[state].child(byte) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.nodes[state].fail;
+        }
+    }
+}
+
+impl Matcher for MultiMatcher {
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        self.find_pattern_at(line, start).map(|(_, span)| span)
+    }
+
+    fn pattern_at(&self, line: &str, span: &Range<usize>) -> Option<usize> {
+        self.find_pattern_at(line, span.start)
+            .filter(|(_, found)| found == span)
+            .map(|(pattern, _)| pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_patterns() {
+        let matcher = MultiMatcher::new(&["he", "she", "his", "hers"], false);
+        assert_eq!(matcher.find_pattern_at("ushers", 0), Some((1, 1..4)));
+        assert_eq!(matcher.find_pattern_at("ushers", 2), Some((3, 2..6)));
+        assert_eq!(matcher.find_all("this hershe"), [1..4, 5..9, 9..11]);
+        assert!(!matcher.is_match("hi s"));
+    }
+
+    #[test]
+    fn leftmost_longest() {
+        let matcher = MultiMatcher::new(&["abcd", "bc", "abc", "b"], false);
+        assert_eq!(matcher.find_pattern_at("xabcdx", 0), Some((0, 1..5)));
+        assert_eq!(matcher.find_pattern_at("xabcx", 0), Some((2, 1..4)));
+        assert_eq!(matcher.find_pattern_at("xbcx", 0), Some((1, 1..3)));
+        assert_eq!(matcher.pattern_at("xbcx", &(1..3)), Some(1));
+        assert_eq!(matcher.pattern_at("xbcx", &(1..2)), None);
+    }
+
+    #[test]
+    fn duplicates_and_empty() {
+        let matcher = MultiMatcher::new(&["cat", "cat"], false);
+        assert_eq!(matcher.find_pattern_at("a cat", 0), Some((0, 2..5)));
+
+        let matcher = MultiMatcher::new(&["", "ab"], false);
+        assert_eq!(matcher.find_pattern_at("ab", 0), Some((1, 0..2)));
+        assert_eq!(matcher.find_pattern_at("xab", 0), Some((0, 0..0)));
+
+        let none: [&str; 0] = [];
+        assert!(!MultiMatcher::new(&none, false).is_match("anything"));
+    }
+
+    #[test]
+    fn ignore_case() {
+        let matcher = MultiMatcher::new(&["straße", "ΣΊΣΥΦΟΣ", "id"], true);
+        assert_eq!(matcher.find_pattern_at("DIE STRASSE", 0), Some((0, 4..11)));
+        assert_eq!(matcher.find_pattern_at("ο σίσυφος", 0), Some((1, 3..17)));
+        assert_eq!(matcher.find_pattern_at("an ID", 0), Some((2, 3..5)));
+        // Half of a folded "ß" is not a match.
+        assert!(!MultiMatcher::new(&["s"], true).is_match("ß"));
+    }
+
+    #[test]
+    fn many_patterns() {
+        let ids: Vec<String> = (0..10_000).map(|i| format!("ID-{:05}", i * 7)).collect();
+        let matcher = MultiMatcher::new(&ids, false);
+        assert_eq!(matcher.find_pattern_at("order ID-00693 shipped", 0), Some((99, 6..14)));
+        assert!(!matcher.is_match("order ID-00694 shipped"));
+    }
+}
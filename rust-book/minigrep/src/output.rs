@@ -0,0 +1,625 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::matcher::Matcher;
+use crate::Match;
+
+// The colours grep uses by default.
+const COLOR_PATH: &str = "\x1b[35m";
+const COLOR_LINE_NUMBER: &str = "\x1b[32m";
+const COLOR_SEPARATOR: &str = "\x1b[36m";
+const COLOR_MATCH: &str = "\x1b[1;31m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// What to print for each searched file.
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// `-n`: prefix lines with their line number.
+    pub line_numbers: bool,
+    /// `-B`: lines of context to print before each match.
+    pub before_context: usize,
+    /// `-A`: lines of context to print after each match.
+    pub after_context: usize,
+    /// `-c`: only print how many lines matched.
+    pub count: bool,
+    /// `-l`: only print the names of files with a match.
+    pub files_with_matches: bool,
+    /// `-v`: select the lines that do *not* match.
+    pub invert: bool,
+    /// `--replace`: print selected lines with their matches replaced.
+    pub replace: Option<String>,
+    /// `--sort-distance`: print the selected lines of each file closest
+    /// match first, rather than in file order. Context lines are dropped.
+    pub sort_by_distance: bool,
+    /// `--json`: print JSON Lines records instead of text. `-c` and `-l`
+    /// don't apply, the counts are in the `end` and `summary` records.
+    pub json: bool,
+    /// Prefix lines with the path of their file.
+    pub with_filename: bool,
+    /// Highlight matches and prefixes with ANSI colours.
+    pub color: bool,
+}
+
+/// Totals over every file a `Printer` printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Stats {
+    pub searches: usize,
+    pub searches_with_match: usize,
+    pub matched_lines: usize,
+}
+
+/// One line of `--json` output, shaped after ripgrep's: the kind of record
+/// in `type` and its fields in `data`.
+#[derive(Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+enum Record<'a> {
+    Begin {
+        path: Option<Cow<'a, str>>,
+    },
+    Match {
+        path: Option<Cow<'a, str>>,
+        line_number: usize,
+        line: &'a str,
+        submatches: Vec<Submatch<'a>>,
+        /// Only with `--fuzzy`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        distance: Option<usize>,
+    },
+    Context {
+        path: Option<Cow<'a, str>>,
+        line_number: usize,
+        line: &'a str,
+    },
+    End {
+        path: Option<Cow<'a, str>>,
+        matched_lines: usize,
+    },
+    Summary {
+        stats: Stats,
+    },
+}
+
+#[derive(Serialize)]
+struct Submatch<'a> {
+    #[serde(rename = "match")]
+    text: &'a str,
+    /// Byte offsets into the line.
+    start: usize,
+    end: usize,
+    /// Which of several patterns matched, by its index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern: Option<usize>,
+}
+
+impl<'a> Record<'a> {
+    fn matched(path: Option<&'a Path>, m: &Match<'a>, matcher: &dyn Matcher) -> Record<'a> {
+        let submatch = |span: &Range<usize>| {
+            Some(Submatch {
+                text: m.line.get(span.clone())?,
+                start: span.start,
+                end: span.end,
+                pattern: matcher.pattern_at(m.line, span),
+            })
+        };
+        Record::Match {
+            path: json_path(path),
+            line_number: m.line_number,
+            line: m.line,
+            submatches: m.spans.iter().filter_map(submatch).collect(),
+            distance: matcher.distance(m.line),
+        }
+    }
+
+    fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        serde_json::to_writer(&mut *out, self)?;
+        writeln!(out)
+    }
+}
+
+/// Stdin has no path, its records have a null one.
+fn json_path(path: Option<&Path>) -> Option<Cow<'_, str>> {
+    path.map(|p| p.to_string_lossy())
+}
+
+/// Writes search results for any number of files to `out`.
+pub struct Printer<W: Write> {
+    out: W,
+    options: OutputOptions,
+    /// Whether a group of lines has already been printed, meaning the next
+    /// non-contiguous one needs a `--` separator.
+    printed_group: bool,
+    /// How many lines were printed, for `resume` to tell whether another
+    /// file printed some in the meantime.
+    printed_lines: usize,
+    stats: Stats,
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(out: W, options: OutputOptions) -> Printer<W> {
+        Printer { out, options, printed_group: false, printed_lines: 0, stats: Stats::default() }
+    }
+
+    /// Starts printing the results for one file. Feed it every line of the
+    /// file in order, then call `finish`.
+    pub fn file<'p>(&'p mut self, matcher: &'p dyn Matcher, path: Option<&'p Path>) -> FilePrinter<'p, W> {
+        self.resume(matcher, path, FileState::default())
+    }
+
+    /// Carries on printing a file where `FilePrinter::pause` left it, for a
+    /// file whose lines come in batches.
+    pub fn resume<'p>(
+        &'p mut self,
+        matcher: &'p dyn Matcher,
+        path: Option<&'p Path>,
+        mut state: FileState,
+    ) -> FilePrinter<'p, W> {
+        // Our last line is no longer the last one out.
+        if state.printed_lines != self.printed_lines {
+            state.last_printed = None;
+        }
+        FilePrinter { printer: self, matcher, path, state }
+    }
+
+    /// A printer with the same options that writes to memory, so a file can
+    /// be searched on another thread and its output `append`ed later.
+    pub fn buffered(&self) -> Printer<Vec<u8>> {
+        self.detached(Vec::new())
+    }
+
+    /// A printer with the same options that writes to `out` instead, whose
+    /// output can be passed back to `write_output` piece by piece.
+    pub fn detached<O: Write>(&self, out: O) -> Printer<O> {
+        Printer::new(out, self.options.clone())
+    }
+
+    /// Writes out everything a `buffered` printer printed, as if it had been
+    /// printed here directly.
+    pub fn append(&mut self, other: Printer<Vec<u8>>) -> io::Result<()> {
+        self.write_output(&other.out, true)?;
+        self.add_stats(other.stats);
+        Ok(())
+    }
+
+    /// Writes part of the output of a `detached` printer, in order.
+    /// `first` says whether it's the first part of that printer's output.
+    pub fn write_output(&mut self, output: &[u8], first: bool) -> io::Result<()> {
+        if output.is_empty() {
+            return Ok(());
+        }
+        // Whatever prints lines in groups starts with a new group, which
+        // needs separating from ours.
+        let options = &self.options;
+        let groups = (options.before_context > 0 || options.after_context > 0)
+            && !options.json && !options.count && !options.files_with_matches;
+        if groups && first && self.printed_group {
+            write_group_separator(&mut self.out, self.options.color)?;
+        }
+        self.out.write_all(output)?;
+        self.printed_group |= groups;
+        Ok(())
+    }
+
+    /// Counts the files a `detached` printer searched in our statistics.
+    pub fn add_stats(&mut self, stats: Stats) {
+        self.stats.searches += stats.searches;
+        self.stats.searches_with_match += stats.searches_with_match;
+        self.stats.matched_lines += stats.matched_lines;
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Prints what comes after the last file: the `summary` record with
+    /// `--json`, nothing otherwise.
+    pub fn summary(&mut self) -> io::Result<()> {
+        if self.options.json {
+            Record::Summary { stats: self.stats }.write(&mut self.out)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Prints the results of a single file, line by line.
+pub struct FilePrinter<'p, W: Write> {
+    printer: &'p mut Printer<W>,
+    matcher: &'p dyn Matcher,
+    path: Option<&'p Path>,
+    state: FileState,
+}
+
+/// Where the printing of a file is at, between `FilePrinter::pause` and
+/// `Printer::resume`.
+#[derive(Default)]
+pub struct FileState {
+    /// The last few lines we didn't print, in case a match comes next.
+    before: VecDeque<(usize, String)>,
+    /// How many more lines to print after the last match.
+    after_left: usize,
+    last_printed: Option<usize>,
+    /// The printer's count of printed lines after our last one.
+    printed_lines: usize,
+    matched: usize,
+    /// Whether the `--json` begin record is out.
+    begun: bool,
+    /// With `--sort-distance`, the selected lines waiting for the end of the
+    /// file, with their distance.
+    by_distance: Vec<(usize, usize, String)>,
+}
+
+impl<'p, W: Write> FilePrinter<'p, W> {
+    /// Handles the next line of the file. `number` starts at 1.
+    ///
+    /// Returns `false` once the rest of the file can't change the output, so
+    /// the caller may stop reading.
+    pub fn line(&mut self, number: usize, line: &str) -> io::Result<bool> {
+        self.begin()?;
+        let options = &self.printer.options;
+        let selected = self.matcher.is_match(line) != options.invert;
+        let before_context = options.before_context;
+
+        if selected {
+            self.state.matched += 1;
+            if options.files_with_matches && !options.json {
+                return Ok(false);
+            }
+            if options.count && !options.json {
+                return Ok(true);
+            }
+            if options.sort_by_distance {
+                let distance = self.matcher.distance(line).unwrap_or(0);
+                self.state.by_distance.push((distance, number, line.to_string()));
+                return Ok(true);
+            }
+
+            self.state.after_left = options.after_context;
+            while let Some((before_number, before_line)) = self.state.before.pop_front() {
+                self.print_line(before_number, &before_line, false)?;
+            }
+            self.print_line(number, line, true)?;
+        } else if self.state.after_left > 0 {
+            self.state.after_left -= 1;
+            self.print_line(number, line, false)?;
+        } else if before_context > 0 {
+            if self.state.before.len() == before_context {
+                self.state.before.pop_front();
+            }
+            self.state.before.push_back((number, line.to_string()));
+        }
+        Ok(true)
+    }
+
+    /// How many lines were selected so far.
+    pub fn selected(&self) -> usize {
+        self.state.matched
+    }
+
+    /// Stops printing the file for now, to `Printer::resume` later.
+    pub fn pause(self) -> FileState {
+        self.state
+    }
+
+    /// Prints the per-file summaries and returns how many lines were
+    /// selected.
+    pub fn finish(mut self) -> io::Result<usize> {
+        self.begin()?;
+        // Stable, so equally close lines stay in file order.
+        let mut by_distance = std::mem::take(&mut self.state.by_distance);
+        by_distance.sort_by_key(|&(distance, _, _)| distance);
+        for (_, number, line) in by_distance {
+            self.print_line(number, &line, true)?;
+        }
+
+        let stats = &mut self.printer.stats;
+        stats.searches += 1;
+        stats.searches_with_match += (self.state.matched > 0) as usize;
+        stats.matched_lines += self.state.matched;
+
+        let color = self.printer.options.color;
+        let out = &mut self.printer.out;
+        if self.printer.options.json {
+            Record::End { path: json_path(self.path), matched_lines: self.state.matched }.write(out)?;
+        } else if self.printer.options.files_with_matches {
+            if self.state.matched > 0 {
+                write_path(out, self.path, color)?;
+                writeln!(out)?;
+            }
+        } else if self.printer.options.count {
+            if self.printer.options.with_filename {
+                write_path(out, self.path, color)?;
+                write_separator(out, ':', color)?;
+            }
+            writeln!(out, "{}", self.state.matched)?;
+        }
+        Ok(self.state.matched)
+    }
+
+    fn begin(&mut self) -> io::Result<()> {
+        if self.printer.options.json && !self.state.begun {
+            Record::Begin { path: json_path(self.path) }.write(&mut self.printer.out)?;
+        }
+        self.state.begun = true;
+        Ok(())
+    }
+
+    fn print_line(&mut self, number: usize, line: &str, selected: bool) -> io::Result<()> {
+        let options = &self.printer.options;
+        let color = options.color;
+        let has_context = options.before_context > 0 || options.after_context > 0;
+        let out = &mut self.printer.out;
+
+        if options.json {
+            if selected {
+                let spans = if options.invert { Vec::new() } else { self.matcher.find_all(line) };
+                let m = Match { line_number: number, line, spans };
+                Record::matched(self.path, &m, self.matcher).write(out)?;
+            } else {
+                Record::Context { path: json_path(self.path), line_number: number, line }.write(out)?;
+            }
+            self.state.last_printed = Some(number);
+            return Ok(());
+        }
+
+        let contiguous = self.state.last_printed.is_some_and(|last| last + 1 == number);
+        if has_context && self.printer.printed_group && !contiguous {
+            write_group_separator(out, color)?;
+        }
+
+        // grep marks selected lines with ':' and context lines with '-'.
+        let separator = if selected { ':' } else { '-' };
+        if options.with_filename {
+            write_path(out, self.path, color)?;
+            write_separator(out, separator, color)?;
+        }
+        if options.line_numbers {
+            if color {
+                write!(out, "{}{}{}", COLOR_LINE_NUMBER, number, COLOR_RESET)?;
+            } else {
+                write!(out, "{}", number)?;
+            }
+            write_separator(out, separator, color)?;
+        }
+        if let Some(distance) = self.matcher.distance(line).filter(|_| selected) {
+            write!(out, "~{}", distance)?;
+            write_separator(out, separator, color)?;
+        }
+
+        if let (Some(template), true) = (&options.replace, selected && !options.invert) {
+            write!(out, "{}", self.matcher.replace_all(line, template))?;
+        } else if color && selected && !options.invert {
+            write_highlighted(out, line, &self.matcher.find_all(line))?;
+        } else {
+            write!(out, "{}", line)?;
+        }
+        writeln!(out)?;
+
+        self.state.last_printed = Some(number);
+        self.printer.printed_group = true;
+        self.printer.printed_lines += 1;
+        self.state.printed_lines = self.printer.printed_lines;
+        Ok(())
+    }
+}
+
+fn write_path<W: Write>(out: &mut W, path: Option<&Path>, color: bool) -> io::Result<()> {
+    let path = path.map_or("(standard input)".into(), |p| p.to_string_lossy());
+    if color {
+        write!(out, "{}{}{}", COLOR_PATH, path, COLOR_RESET)
+    } else {
+        write!(out, "{}", path)
+    }
+}
+
+fn write_group_separator<W: Write>(out: &mut W, color: bool) -> io::Result<()> {
+    if color {
+        writeln!(out, "{}--{}", COLOR_SEPARATOR, COLOR_RESET)
+    } else {
+        writeln!(out, "--")
+    }
+}
+
+fn write_separator<W: Write>(out: &mut W, separator: char, color: bool) -> io::Result<()> {
+    if color {
+        write!(out, "{}{}{}", COLOR_SEPARATOR, separator, COLOR_RESET)
+    } else {
+        write!(out, "{}", separator)
+    }
+}
+
+fn write_highlighted<W: Write>(out: &mut W, line: &str, spans: &[Range<usize>]) -> io::Result<()> {
+    let mut last = 0;
+    for span in spans {
+        // Never slice through a character, even if a matcher got it wrong.
+        if span.start < last || !line.is_char_boundary(span.start) || !line.is_char_boundary(span.end) {
+            continue;
+        }
+        write!(out, "{}{}{}{}", &line[last..span.start], COLOR_MATCH, &line[span.clone()], COLOR_RESET)?;
+        last = span.end;
+    }
+    write!(out, "{}", &line[last..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzy::FuzzyMatcher;
+    use crate::matcher::LiteralMatcher;
+    use crate::multi::MultiMatcher;
+
+    const POEM: &str = "\
+one fish
+two fish
+red fish
+blue fish
+black cat
+white cat
+old fish
+new fish";
+
+    fn print_file<W: Write>(printer: &mut Printer<W>, matcher: &dyn Matcher, path: &str, contents: &str) {
+        let mut file = printer.file(matcher, Some(Path::new(path)));
+        for (i, line) in contents.lines().enumerate() {
+            if !file.line(i + 1, line).unwrap() {
+                break;
+            }
+        }
+        file.finish().unwrap();
+    }
+
+    fn print(options: OutputOptions, query: &str, files: &[(&str, &str)]) -> String {
+        let matcher = LiteralMatcher::new(query);
+        let mut printer = Printer::new(Vec::new(), options);
+        for (path, contents) in files {
+            print_file(&mut printer, &matcher, path, contents);
+        }
+        String::from_utf8(printer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn plain() {
+        let out = print(OutputOptions::default(), "cat", &[("poem", POEM)]);
+        assert_eq!(out, "black cat\nwhite cat\n");
+    }
+
+    #[test]
+    fn line_numbers_and_filename() {
+        let options = OutputOptions { line_numbers: true, with_filename: true, ..Default::default() };
+        let out = print(options, "cat", &[("poem", POEM)]);
+        assert_eq!(out, "poem:5:black cat\npoem:6:white cat\n");
+    }
+
+    #[test]
+    fn context() {
+        let options = OutputOptions { line_numbers: true, before_context: 1, after_context: 1, ..Default::default() };
+        let out = print(options, "o", &[("poem", POEM)]);
+        assert_eq!(out, "1:one fish\n2:two fish\n3-red fish\n--\n6-white cat\n7:old fish\n8-new fish\n");
+
+        let options = OutputOptions { before_context: 2, ..Default::default() };
+        let out = print(options, "white", &[("poem", POEM)]);
+        assert_eq!(out, "blue fish\nblack cat\nwhite cat\n");
+
+        // Groups from different files are separated too.
+        let options = OutputOptions { after_context: 1, with_filename: true, ..Default::default() };
+        let out = print(options, "new", &[("a", POEM), ("b", POEM)]);
+        assert_eq!(out, "a:new fish\n--\nb:new fish\n");
+    }
+
+    #[test]
+    fn buffered() {
+        let matcher = LiteralMatcher::new("new");
+        let options = OutputOptions { after_context: 1, with_filename: true, ..Default::default() };
+        let mut printer = Printer::new(Vec::new(), options.clone());
+        for path in ["a", "b"] {
+            let mut buffer = printer.buffered();
+            print_file(&mut buffer, &matcher, path, POEM);
+            printer.append(buffer).unwrap();
+        }
+        let out = String::from_utf8(printer.into_inner()).unwrap();
+        assert_eq!(out, print(options, "new", &[("a", POEM), ("b", POEM)]));
+    }
+
+    #[test]
+    fn invert() {
+        let options = OutputOptions { invert: true, ..Default::default() };
+        let out = print(options, "fish", &[("poem", POEM)]);
+        assert_eq!(out, "black cat\nwhite cat\n");
+    }
+
+    #[test]
+    fn count_and_files_with_matches() {
+        let options = OutputOptions { count: true, with_filename: true, ..Default::default() };
+        let out = print(options, "cat", &[("poem", POEM), ("empty", "")]);
+        assert_eq!(out, "poem:2\nempty:0\n");
+
+        let options = OutputOptions { count: true, invert: true, ..Default::default() };
+        let out = print(options, "cat", &[("poem", POEM)]);
+        assert_eq!(out, "6\n");
+
+        let options = OutputOptions { files_with_matches: true, ..Default::default() };
+        let out = print(options, "cat", &[("poem", POEM), ("empty", ""), ("again", POEM)]);
+        assert_eq!(out, "poem\nagain\n");
+    }
+
+    #[test]
+    fn replace() {
+        let options = OutputOptions { replace: Some("dog".to_string()), after_context: 1, ..Default::default() };
+        let out = print(options, "cat", &[("poem", POEM)]);
+        assert_eq!(out, "black dog
+white dog
+old fish
+");
+    }
+
+    #[test]
+    fn json() {
+        let options = OutputOptions { json: true, count: true, before_context: 1, ..Default::default() };
+        let matcher = LiteralMatcher::new("fish");
+        let mut printer = Printer::new(Vec::new(), options);
+        print_file(&mut printer, &matcher, "a", "cat\nfish, fish\nλ fish");
+        print_file(&mut printer, &matcher, "b", "dog");
+        printer.summary().unwrap();
+        assert_eq!(printer.stats(), Stats { searches: 2, searches_with_match: 1, matched_lines: 2 });
+
+        let out = String::from_utf8(printer.into_inner()).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines, [
+            r#"{"type":"begin","data":{"path":"a"}}"#,
+            r#"{"type":"context","data":{"path":"a","line_number":1,"line":"cat"}}"#,
+            r#"{"type":"match","data":{"path":"a","line_number":2,"line":"fish, fish","submatches":[{"match":"fish","start":0,"end":4},{"match":"fish","start":6,"end":10}]}}"#,
+            r#"{"type":"match","data":{"path":"a","line_number":3,"line":"λ fish","submatches":[{"match":"fish","start":3,"end":7}]}}"#,
+            r#"{"type":"end","data":{"path":"a","matched_lines":2}}"#,
+            r#"{"type":"begin","data":{"path":"b"}}"#,
+            r#"{"type":"end","data":{"path":"b","matched_lines":0}}"#,
+            r#"{"type":"summary","data":{"stats":{"searches":2,"searches_with_match":1,"matched_lines":2}}}"#,
+        ]);
+    }
+
+    #[test]
+    fn fuzzy() {
+        let matcher = FuzzyMatcher::new("fish", 1, false).unwrap();
+        let options = OutputOptions { line_numbers: true, sort_by_distance: true, ..Default::default() };
+        let mut printer = Printer::new(Vec::new(), options);
+        print_file(&mut printer, &matcher, "poem", "red dish\nblue fish\nold fsh\nno");
+        let out = String::from_utf8(printer.into_inner()).unwrap();
+        assert_eq!(out, "2:~0:blue fish\n1:~1:red dish\n3:~1:old fsh\n");
+    }
+
+    #[test]
+    fn json_patterns() {
+        let matcher = MultiMatcher::new(&["cat", "dog"], false);
+        let mut printer = Printer::new(Vec::new(), OutputOptions { json: true, ..Default::default() });
+        print_file(&mut printer, &matcher, "a", "dog and cat");
+        let out = String::from_utf8(printer.into_inner()).unwrap();
+        assert_eq!(
+            out.lines().nth(1),
+            Some(r#"{"type":"match","data":{"path":"a","line_number":1,"line":"dog and cat","submatches":[{"match":"dog","start":0,"end":3,"pattern":1},{"match":"cat","start":8,"end":11,"pattern":0}]}}"#)
+        );
+    }
+
+    #[test]
+    fn color() {
+        let options = OutputOptions { color: true, line_numbers: true, ..Default::default() };
+        let out = print(options, "fish", &[("poem", "a fish, fish")]);
+        assert_eq!(
+            out,
+            "\x1b[32m1\x1b[0m\x1b[36m:\x1b[0ma \x1b[1;31mfish\x1b[0m, \x1b[1;31mfish\x1b[0m\n"
+        );
+    }
+}
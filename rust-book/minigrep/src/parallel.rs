@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+use crate::matcher::Matcher;
+use crate::output::{Printer, Stats};
+
+/// How much output a file being searched ahead of its turn may hold before
+/// its worker waits for that turn.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// How many files per thread may be searched ahead of the one being printed.
+const AHEAD_PER_THREAD: usize = 2;
+
+/// What a worker reports about file `index`.
+enum Event {
+    /// More output of the file being printed.
+    Output { index: usize, bytes: Vec<u8> },
+    /// The end of the file's output, and how many of its lines were
+    /// selected.
+    Done { index: usize, bytes: Vec<u8>, result: Result<(Stats, usize), String> },
+}
+
+/// Which file is being printed, for the workers to wait on.
+struct Turn {
+    /// The index of the file being printed, or `None` once the search was
+    /// abandoned.
+    current: Mutex<Option<usize>>,
+    changed: Condvar,
+}
+
+impl Turn {
+    /// Blocks until `ready` holds for the file being printed.
+    fn wait(&self, ready: impl Fn(usize) -> bool) -> io::Result<()> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            match *current {
+                None => return Err(io::Error::new(io::ErrorKind::Interrupted, "search abandoned")),
+                Some(index) if ready(index) => return Ok(()),
+                Some(_) => current = self.changed.wait(current).unwrap_or_else(|e| e.into_inner()),
+            }
+        }
+    }
+
+    fn set(&self, current: Option<usize>) {
+        *self.current.lock().unwrap_or_else(|e| e.into_inner()) = current;
+        self.changed.notify_all();
+    }
+}
+
+/// Where a worker's printer writes: straight to the main thread when its
+/// file is the one being printed, into a bounded buffer otherwise.
+struct ChunkWriter<'a> {
+    index: usize,
+    buffer: Vec<u8>,
+    sender: Sender<Event>,
+    turn: &'a Turn,
+}
+
+impl Write for ChunkWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_LEN {
+            self.turn.wait(|current| current == self.index)?;
+            let bytes = mem::take(&mut self.buffer);
+            self.sender.send(Event::Output { index: self.index, bytes })
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "output closed"))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Searches `files` on `threads` worker threads and prints the results to
+/// `printer`.
+///
+/// The output is exactly that of a serial search: the file whose turn it is
+/// gets printed as it is searched, while the next few files are searched
+/// ahead into buffers of at most `CHUNK_LEN` bytes, so memory use stays
+/// bounded whatever the size of the files. Errors are reported in order too.
+/// Returns how many lines were selected and how many paths could not be
+/// searched.
+pub fn search_files<W: Write>(
+    matcher: &dyn Matcher,
+    files: impl Iterator<Item = Result<PathBuf, Box<dyn Error>>>,
+    threads: usize,
+    search_zip: bool,
+    printer: &mut Printer<W>,
+) -> io::Result<(usize, usize)> {
+    // Walk errors aren't `Send`, their messages are.
+    let files: Vec<Result<PathBuf, String>> = files.map(|f| f.map_err(|e| e.to_string())).collect();
+    let template = printer.buffered();
+    let next = AtomicUsize::new(0);
+    let turn = Turn { current: Mutex::new(Some(0)), changed: Condvar::new() };
+    let ahead = threads * AHEAD_PER_THREAD;
+    let (sender, receiver) = mpsc::channel::<Event>();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.min(files.len()) {
+            let sender = sender.clone();
+            let (files, template, next, turn) = (&files, &template, &next, &turn);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let path = match files.get(index) {
+                    Some(path) => path,
+                    None => break,
+                };
+                if turn.wait(|current| index < current + ahead).is_err() {
+                    break;
+                }
+                let out = ChunkWriter { index, buffer: Vec::new(), sender: sender.clone(), turn };
+                let mut file_printer = template.detached(out);
+                let result = path.clone().and_then(|path| {
+                    crate::search_file(matcher, &path, search_zip, &mut file_printer).map_err(|e| e.to_string())
+                });
+                let stats = file_printer.stats();
+                let bytes = file_printer.into_inner().buffer;
+                let result = result.map(|selected| (stats, selected));
+                if sender.send(Event::Done { index, bytes, result }).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let result = print_in_order(receiver, files.len(), &turn, printer);
+        // Wakes up any worker still waiting for its turn.
+        turn.set(None);
+        result
+    })
+}
+
+/// Prints what the workers send, one file after another.
+fn print_in_order<W: Write>(
+    receiver: mpsc::Receiver<Event>,
+    count: usize,
+    turn: &Turn,
+    printer: &mut Printer<W>,
+) -> io::Result<(usize, usize)> {
+    // Files done before their turn, waiting for it.
+    let mut pending = BTreeMap::new();
+    let mut current = 0;
+    // Whether some of the current file's output is already out.
+    let mut started = false;
+    let (mut selected, mut failures) = (0, 0);
+    for event in receiver {
+        match event {
+            Event::Output { index, bytes } => {
+                debug_assert_eq!(index, current, "only the current file streams");
+                printer.write_output(&bytes, !started)?;
+                started |= !bytes.is_empty();
+            }
+            Event::Done { index, bytes, result } => {
+                pending.insert(index, (bytes, result));
+                while let Some((bytes, result)) = pending.remove(&current) {
+                    printer.write_output(&bytes, !started)?;
+                    match result {
+                        Ok((stats, count)) => {
+                            printer.add_stats(stats);
+                            selected += count;
+                        }
+                        Err(e) => {
+                            eprintln!("minigrep: {}", e);
+                            failures += 1;
+                        }
+                    }
+                    current += 1;
+                    started = false;
+                    turn.set(Some(current));
+                }
+            }
+        }
+    }
+    debug_assert_eq!(current, count, "every file was printed");
+    Ok((selected, failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use crate::matcher::LiteralMatcher;
+    use crate::output::OutputOptions;
+
+    #[test]
+    fn same_output_as_serial() {
+        let root = std::env::temp_dir().join(format!("minigrep-parallel-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let mut paths = Vec::new();
+        for i in 0..20 {
+            let path = root.join(format!("{}.txt", i));
+            // Files of very different sizes, so they finish out of order,
+            // some with more output than a chunk.
+            let lines = (0..(20 - i) * 500).map(|n| format!("line {} of file {}\n", n, i));
+            fs::write(&path, lines.collect::<String>()).unwrap();
+            paths.push(path);
+        }
+        paths.insert(3, root.join("missing.txt"));
+
+        let matcher = LiteralMatcher::new("9");
+        for options in [
+            OutputOptions { with_filename: true, line_numbers: true, ..Default::default() },
+            OutputOptions { with_filename: true, before_context: 1, ..Default::default() },
+        ] {
+            let search = |threads| {
+                let mut printer = Printer::new(Vec::new(), options.clone());
+                let files = paths.iter().map(|p| Ok(p.clone()));
+                let counts = search_files(&matcher, files, threads, false, &mut printer).unwrap();
+                (counts, printer.stats(), printer.into_inner())
+            };
+
+            let (serial_counts, serial_stats, serial) = search(1);
+            let (parallel_counts, parallel_stats, parallel) = search(4);
+            assert_eq!(serial_counts, parallel_counts);
+            assert_eq!(serial_counts.1, 1);
+            assert!(serial_counts.0 > 0);
+            assert!(serial.len() > 2 * CHUNK_LEN);
+            assert_eq!(serial_stats, parallel_stats);
+            assert!(serial == parallel);
+        }
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
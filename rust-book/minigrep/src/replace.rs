@@ -0,0 +1,183 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::matcher::Matcher;
+use crate::walk;
+
+/// A line that `--replace` changed.
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    /// Starts at 1, like the `-n` output.
+    pub line_number: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// Replaces the matches on every line of `contents` with `template`,
+/// keeping the line endings as they were. Returns the new contents and the
+/// lines that changed.
+pub fn replace_lines(matcher: &dyn Matcher, template: &str, contents: &str) -> (String, Vec<Change>) {
+    let mut replaced = String::with_capacity(contents.len());
+    let mut changes = Vec::new();
+    for (i, raw) in contents.split_inclusive('\n').enumerate() {
+        let line = raw.trim_end_matches('\n').trim_end_matches('\r');
+        if !matcher.is_match(line) {
+            replaced.push_str(raw);
+            continue;
+        }
+        let new = matcher.replace_all(line, template);
+        replaced.push_str(&new);
+        replaced.push_str(&raw[line.len()..]);
+        if new != line {
+            changes.push(Change { line_number: i + 1, old: line.to_string(), new });
+        }
+    }
+    (replaced, changes)
+}
+
+/// `--in-place`: rewrites each file with its matches replaced, or with
+/// `dry_run` only prints what would change as a unified diff to `out`.
+///
+/// Returns how many lines changed and how many paths could not be edited,
+/// reporting the errors on stderr like a search does.
+pub fn edit_files<W: Write>(
+    matcher: &dyn Matcher,
+    template: &str,
+    files: impl Iterator<Item = Result<PathBuf, Box<dyn Error>>>,
+    dry_run: bool,
+    out: &mut W,
+) -> io::Result<(usize, usize)> {
+    let (mut changed, mut failures) = (0, 0);
+    for path in files {
+        match path.and_then(|path| edit_file(matcher, template, &path, dry_run, out)) {
+            Ok(count) => changed += count,
+            Err(e) => {
+                eprintln!("minigrep: {}", e);
+                failures += 1;
+            }
+        }
+    }
+    Ok((changed, failures))
+}
+
+fn edit_file<W: Write>(
+    matcher: &dyn Matcher,
+    template: &str,
+    path: &Path,
+    dry_run: bool,
+    out: &mut W,
+) -> Result<usize, Box<dyn Error>> {
+    if path == Path::new(walk::STDIN) {
+        return Err("(standard input): can't be edited in place".into());
+    }
+
+    let with_path = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let bytes = fs::read(path).map_err(|e| with_path(&e))?;
+    if walk::is_binary(&bytes) {
+        return Ok(0);
+    }
+    // Unlike a search, an edit can't get away with replacing invalid bytes.
+    let contents = String::from_utf8(bytes).map_err(|e| with_path(&e))?;
+
+    let (replaced, changes) = replace_lines(matcher, template, &contents);
+    if changes.is_empty() {
+        return Ok(0);
+    }
+    if dry_run {
+        write_diff(out, path, &changes)?;
+    } else {
+        write_atomically(path, replaced.as_bytes()).map_err(|e| with_path(&e))?;
+    }
+    Ok(changes.len())
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over
+/// `path`, so readers see either the old file or the new one, never half of
+/// it. The new file keeps the permissions of the old one.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.minigrep-{}.tmp", name, process::id()));
+
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::set_permissions(&temp, fs::metadata(path)?.permissions())?;
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+fn write_diff<W: Write>(out: &mut W, path: &Path, changes: &[Change]) -> io::Result<()> {
+    writeln!(out, "--- {}", path.display())?;
+    writeln!(out, "+++ {}", path.display())?;
+    for change in changes {
+        writeln!(out, "@@ -{0},1 +{0},1 @@", change.line_number)?;
+        writeln!(out, "-{}", change.old)?;
+        writeln!(out, "+{}", change.new)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::matcher::{LiteralMatcher, RegexMatcher};
+
+    #[test]
+    fn literal_template() {
+        let matcher = LiteralMatcher::new("cat");
+        let (replaced, changes) = replace_lines(&matcher, "dog", "a cat\r\nno\nthe cat, the cat");
+        assert_eq!(replaced, "a dog\r\nno\nthe dog, the dog");
+        assert_eq!(changes, [
+            Change { line_number: 1, old: "a cat".to_string(), new: "a dog".to_string() },
+            Change { line_number: 3, old: "the cat, the cat".to_string(), new: "the dog, the dog".to_string() },
+        ]);
+
+        // In fixed-string mode, `$` is nothing special.
+        assert_eq!(replace_lines(&matcher, "$1", "cat\n").0, "$1\n");
+    }
+
+    #[test]
+    fn capture_groups() {
+        let matcher = RegexMatcher::new(r"(\w+)@(?P<host>\w+)", false, false).unwrap();
+        let (replaced, changes) = replace_lines(&matcher, "${host}: $1", "mail bob@home\nnone\n");
+        assert_eq!(replaced, "mail home: bob\nnone\n");
+        assert_eq!(changes.len(), 1);
+    }
+
+    #[test]
+    fn in_place() {
+        let root = std::env::temp_dir().join(format!("minigrep-replace-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("poem.txt");
+        fs::write(&path, "one fish\ntwo fish\nred cat\n").unwrap();
+        let matcher = LiteralMatcher::new("fish");
+        let files = || vec![Ok(path.clone())].into_iter();
+
+        let mut diff = Vec::new();
+        assert_eq!(edit_files(&matcher, "cod", files(), true, &mut diff).unwrap(), (2, 0));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one fish\ntwo fish\nred cat\n");
+        let name = path.display();
+        assert_eq!(
+            String::from_utf8(diff).unwrap(),
+            format!("--- {0}\n+++ {0}\n@@ -1,1 +1,1 @@\n-one fish\n+one cod\n@@ -2,1 +2,1 @@\n-two fish\n+two cod\n", name)
+        );
+
+        let mut out = Vec::new();
+        assert_eq!(edit_files(&matcher, "cod", files(), false, &mut out).unwrap(), (2, 0));
+        assert!(out.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one cod\ntwo cod\nred cat\n");
+        // Nothing is left behind next to the file.
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 1);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
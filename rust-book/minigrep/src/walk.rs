@@ -0,0 +1,175 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+
+/// How many leading bytes we look at to decide whether a file is binary.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+
+/// The path that stands for standard input.
+pub const STDIN: &str = "-";
+
+/// `--include`/`--exclude` globs, matched against file names.
+#[derive(Default)]
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl FileFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<FileFilter, Box<dyn Error>> {
+        Ok(FileFilter {
+            include: build_glob_set(include)?,
+            exclude: build_glob_set(exclude)?,
+        })
+    }
+
+    /// A file is searched when it matches an include glob (if there are any)
+    /// and no exclude glob.
+    pub fn is_match(&self, path: &Path) -> bool {
+        let name = match path.file_name() {
+            Some(name) => name,
+            None => return true,
+        };
+        let included = self.include.as_ref().is_none_or(|set| set.is_match(name));
+        let excluded = self.exclude.as_ref().is_some_and(|set| set.is_match(name));
+        included && !excluded
+    }
+}
+
+fn build_glob_set(globs: &[String]) -> Result<Option<GlobSet>, Box<dyn Error>> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob)?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Lists the files to search under the given paths, in argument order.
+///
+/// Files named explicitly are always listed (if they pass the filter), and
+/// so is `-` for stdin.
+/// Directories are only walked when `recursive` is set, skipping hidden
+/// entries and whatever `.gitignore` files ask us to skip; otherwise they
+/// show up as an error, like with grep.
+pub fn files<'a>(
+    paths: &'a [String],
+    recursive: bool,
+    filter: &'a FileFilter,
+) -> impl Iterator<Item = Result<PathBuf, Box<dyn Error>>> + 'a {
+    paths.iter().flat_map(move |path| -> Box<dyn Iterator<Item = _>> {
+        let path = Path::new(path);
+        if path == Path::new(STDIN) {
+            Box::new(Some(Ok(path.to_path_buf())).into_iter())
+        } else if !path.is_dir() {
+            Box::new(Some(Ok(path.to_path_buf())).into_iter().filter(move |_| filter.is_match(path)))
+        } else if !recursive {
+            let err = format!("{}: Is a directory", path.display());
+            Box::new(Some(Err(err.into())).into_iter())
+        } else {
+            let walk = WalkBuilder::new(path)
+                .require_git(false)
+                .build();
+            Box::new(walk.filter_map(move |entry| match entry {
+                Ok(entry) => {
+                    let is_file = entry.file_type().is_some_and(|t| t.is_file());
+                    if is_file && filter.is_match(entry.path()) {
+                        Some(Ok(entry.into_path()))
+                    } else {
+                        None
+                    }
+                }
+                Err(err) => Some(Err(err.into())),
+            }))
+        }
+    })
+}
+
+/// Whether `bytes` look like binary data rather than text. Like grep, we call
+/// a file binary when its beginning contains a NUL byte.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    /// Builds a fresh directory tree under the system temp dir.
+    fn tree(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("minigrep-walk-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    fn names(root: &Path, found: Vec<Result<PathBuf, Box<dyn Error>>>) -> Vec<String> {
+        let mut names: Vec<_> = found.into_iter()
+            .map(|p| p.unwrap().strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn filter() {
+        let filter = FileFilter::new(&["*.rs".to_string(), "*.md".to_string()], &["test_*".to_string()]).unwrap();
+        assert!(filter.is_match(Path::new("src/lib.rs")));
+        assert!(filter.is_match(Path::new("README.md")));
+        assert!(!filter.is_match(Path::new("poem.txt")));
+        assert!(!filter.is_match(Path::new("tests/test_lib.rs")));
+
+        assert!(FileFilter::default().is_match(Path::new("anything")));
+        assert!(FileFilter::new(&["[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn recursive_walk() {
+        let root = tree("recursive", &[
+            (".gitignore", b"target/\n*.log\n"),
+            ("a.txt", b"a"),
+            ("sub/b.txt", b"b"),
+            ("sub/c.rs", b"c"),
+            ("sub/debug.log", b"ignored"),
+            ("target/d.txt", b"ignored"),
+        ]);
+        let paths = vec![root.to_string_lossy().into_owned()];
+
+        let all = FileFilter::default();
+        let found: Vec<_> = files(&paths, true, &all).collect();
+        assert_eq!(names(&root, found), ["a.txt", "sub/b.txt", "sub/c.rs"]);
+
+        let txt = FileFilter::new(&["*.txt".to_string()], &[]).unwrap();
+        let found: Vec<_> = files(&paths, true, &txt).collect();
+        assert_eq!(names(&root, found), ["a.txt", "sub/b.txt"]);
+
+        // Directories are an error unless we recurse.
+        let mut found = files(&paths, false, &all);
+        assert!(found.next().unwrap().is_err());
+        assert!(found.next().is_none());
+
+        // Stdin is never filtered out.
+        let stdin = vec![STDIN.to_string()];
+        let found: Vec<_> = files(&stdin, false, &txt).map(Result::unwrap).collect();
+        assert_eq!(found, [PathBuf::from(STDIN)]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn binary() {
+        assert!(!is_binary(b"plain text\n"));
+        assert!(is_binary(b"\x7fELF\x00\x01"));
+        assert!(!is_binary(b""));
+    }
+}
@@ -200,6 +200,7 @@ fn opaque_read(val: &i32) {
     println!("{}", val);
 }
 
+#[allow(clippy::borrow_deref_ref)]
 pub fn testing_safe_shared_ref() {
     let mut data = 10;
     let mref1 = &mut data;
@@ -64,15 +64,16 @@ impl<T> List<T> {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn into_iter(self) -> IntoIter<T> {
         IntoIter(self)
     }
 
-    pub fn iter(&self) -> Iter<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
         Iter { next: unsafe { self.head.as_ref() } }
     }
 
-    pub fn iter_mut(&self) -> IterMut<T> {
+    pub fn iter_mut(&self) -> IterMut<'_, T> {
         IterMut { next: unsafe { self.head.as_mut() } }
     }
 
@@ -85,6 +86,12 @@ impl<T> List<T> {
     }
 }
 
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Iterator for IntoIter<T> {
     type Item = T;
 
@@ -118,7 +125,7 @@ impl<'a, T> Iterator for IterMut<'a, T> {
 
 impl<T> Drop for List<T> {
     fn drop(&mut self) {
-        while let Some(_) = self.pop() {}
+        while self.pop().is_some() {}
     }
 }
 
@@ -178,7 +185,7 @@ mod test {
 
         assert!(list.peek() == Some(&3));
         list.push(6);
-        list.peek_mut().map(|x| *x *= 10);
+        if let Some(x) = list.peek_mut() { *x *= 10 }
         assert!(list.peek() == Some(&30));
         assert!(list.pop() == Some(30));
 
@@ -194,7 +201,7 @@ mod test {
         assert_eq!(iter.next(), None);
 
         assert!(list.pop() == Some(400));
-        list.peek_mut().map(|x| *x *= 10);
+        if let Some(x) = list.peek_mut() { *x *= 10 }
         assert!(list.peek() == Some(&5000));
         list.push(7);
 
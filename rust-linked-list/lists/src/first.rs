@@ -1,3 +1,4 @@
+use std::hash::{Hash, Hasher};
 use std::mem;
 
 // pub says we want people outside this module to be able to use List
@@ -21,6 +22,26 @@ enum Link {
     More(Box<Node>),
 }
 
+// Walks the elements from the head. Not exposed, it only saves us from
+// writing the same loop in every trait impl below.
+struct Elems<'a> {
+    next: &'a Link,
+}
+
+impl<'a> Iterator for Elems<'a> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next {
+            Link::Empty => None,
+            Link::More(node) => {
+                self.next = &node.next;
+                Some(node.elem)
+            }
+        }
+    }
+}
+
 impl List {
     pub fn new() -> Self {
         List { head: Link::Empty }
@@ -28,7 +49,7 @@ impl List {
 
     pub fn push(&mut self, elem: i32) {
         let new_node = Box::new(Node {
-            elem: elem,
+            elem,
             next: mem::replace(&mut self.head, Link::Empty),
         });
         self.head = Link::More(new_node);
@@ -43,6 +64,52 @@ impl List {
             }
         }
     }
+
+    fn elems(&self) -> Elems<'_> {
+        Elems { next: &self.head }
+    }
+}
+
+// A derived Clone would recurse once per node, just like the derived Drop.
+// Instead we walk the list and keep a pointer to the link we must fill next.
+impl Clone for List {
+    fn clone(&self) -> Self {
+        let mut new_list = List::new();
+        let mut tail = &mut new_list.head;
+        for elem in self.elems() {
+            *tail = Link::More(Box::new(Node { elem, next: Link::Empty }));
+            tail = match tail {
+                Link::More(new_node) => &mut new_node.next,
+                Link::Empty => unreachable!(),
+            };
+        }
+        new_list
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        self.elems().eq(other.elems())
+    }
+}
+
+impl Eq for List {}
+
+impl Hash for List {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // The length first, like slices do: two lists hashed in a row, as
+        // in a tuple, can't collide by splitting the same numbers otherwise.
+        self.elems().count().hash(state);
+        for elem in self.elems() {
+            elem.hash(state);
+        }
+    }
+}
+
+impl Default for List {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Drop for List {
@@ -1,3 +1,6 @@
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+
 // Tuple structs are an alternative form of struct,
 // useful for trivial wrappers around other types.
 pub struct IntoIter<T>(List<T>);
@@ -23,6 +26,15 @@ struct Node<T> {
 // yay type aliases !
 type Link<T> = Option<Box<Node<T>>>;
 
+impl<T> IntoIterator for List<T> {
+    type IntoIter = IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
 impl<T> Iterator for IntoIter<T> {
     type Item = T;
 
@@ -65,10 +77,6 @@ impl<T> List<T> {
         List { head: None }
     }
 
-    pub fn into_iter(self) -> IntoIter<T> {
-        IntoIter(self)
-    }
-
     // We declare a fresh lifetime here for the *exact* borrow that
     // creates the iter. Now &self needs to be valid as long as the
     // Iter is around.
@@ -85,13 +93,13 @@ impl<T> List<T> {
 
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<T> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
         IterMut { next: self.head.as_deref_mut() }
     }
 
     pub fn push(&mut self, elem: T) {
         let new_node = Box::new(Node {
-            elem: elem,
+            elem,
             next: self.head.take(),
         });
         self.head = Some(new_node);
@@ -115,6 +123,120 @@ impl<T> List<T> {
             &mut node.elem
         })
     }
+
+    // We don't keep a counter around, so this walks the whole stack.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        // Drop already knows how to do this without recursing.
+        *self = List::new();
+    }
+
+    pub fn reverse(&mut self) {
+        // Flip every `next` pointer in place, no node gets reallocated.
+        //
+        // Before:
+        //  head -> 1 -> 2 -> 3
+        // After:
+        //  head -> 3 -> 2 -> 1
+        let mut reversed: Link<T> = None;
+        let mut cur_link = self.head.take();
+        while let Some(mut boxed_node) = cur_link {
+            cur_link = boxed_node.next.take();
+            boxed_node.next = reversed;
+            reversed = Some(boxed_node);
+        }
+        self.head = reversed;
+    }
+}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> Self {
+        // Pushing would reverse the order, so we append at the tail instead.
+        // `tail` always points at the `next` link we need to fill.
+        let mut new_list = List::new();
+        let mut tail = &mut new_list.head;
+        for elem in self.iter() {
+            let node = tail.insert(Box::new(Node {
+                elem: elem.clone(),
+                next: None,
+            }));
+            tail = &mut node.next;
+        }
+        new_list
+    }
+}
+
+// Like pushing every item one after the other: the last one ends up on top.
+impl<T> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = List::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T: Debug> Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other)
+    }
+}
+
+impl<T: Eq> Eq for List<T> { }
+
+impl<T: Hash> Hash for List<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // The length first, like `Vec`, so a `List<List<T>>` can't collide
+        // with one holding the same elements split differently.
+        self.len().hash(state);
+        for elem in self.iter() {
+            elem.hash(state);
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type IntoIter = IterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
 }
 
 impl<T> Drop for List<T> {
@@ -176,9 +298,9 @@ mod test {
 
         assert_eq!(list.peek(), Some(&3));
         assert_eq!(list.peek_mut(), Some(&mut 3));
-        list.peek_mut().map(|value| {
+        if let Some(value) = list.peek_mut() {
             *value = 42
-        });
+        }
         assert_eq!(list.peek(), Some(&42));
         assert_eq!(list.pop(), Some(42));
     }
@@ -227,4 +349,121 @@ mod test {
         assert_eq!(iter.next(), Some(&mut 1));
         assert_eq!(iter.next(), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn len_is_empty() {
+        use super::List;
+
+        let mut list = List::new();
+        assert_eq!(list.len(), 0);
+        assert!(list.is_empty());
+
+        list.push(1); list.push(2);
+        assert_eq!(list.len(), 2);
+        assert!(!list.is_empty());
+
+        list.clear();
+        assert_eq!(list.len(), 0);
+        assert_eq!(list.pop(), None);
+    }
+
+    #[test]
+    fn reverse() {
+        use super::List;
+
+        let mut list: List<i32> = List::new();
+        list.reverse();
+        assert_eq!(list.peek(), None);
+
+        list.push(1);
+        list.reverse();
+        assert_eq!(list.peek(), Some(&1));
+
+        list.push(2); list.push(3);
+        list.reverse();
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[1, 2, 3]);
+        assert_eq!(list.pop(), Some(1));
+        list.push(4);
+        list.reverse();
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), &[3, 2, 4]);
+    }
+
+    #[test]
+    fn from_iter_extend() {
+        use super::List;
+
+        // Items are pushed in order, so the last one is on top.
+        let mut list: List<i32> = (1..=3).collect();
+        assert_eq!(list.peek(), Some(&3));
+        list.extend([4, 5]);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn clone_eq() {
+        use super::List;
+
+        let list: List<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        let mut cloned = list.clone();
+        assert_eq!(list, cloned);
+        assert_eq!(cloned.pop().as_deref(), Some("c"));
+        assert!(list != cloned);
+        cloned.push("c".to_string());
+        assert_eq!(list, cloned);
+        cloned.push("d".to_string());
+        assert!(list != cloned);
+
+        let empty: List<String> = List::default();
+        assert_eq!(empty.clone(), List::new());
+        assert!(empty != list);
+    }
+
+    #[test]
+    fn debug() {
+        use super::List;
+
+        let list: List<i32> = (0..5).collect();
+        assert_eq!(format!("{:?}", list), "[4, 3, 2, 1, 0]");
+        let list: List<&str> = List::new();
+        assert_eq!(format!("{:?}", list), "[]");
+    }
+
+    #[test]
+    fn ref_into_iter() {
+        use super::List;
+
+        let mut list: List<i32> = (1..=3).collect();
+        for elem in &mut list {
+            *elem *= 10;
+        }
+        let mut sum = 0;
+        for elem in &list {
+            sum += elem;
+        }
+        assert_eq!(sum, 60);
+
+        // Usable from code generic over iterable containers.
+        fn total<'a, C: IntoIterator<Item = &'a i32>>(c: C) -> i32 {
+            c.into_iter().sum()
+        }
+        assert_eq!(total(&list), 60);
+    }
+
+    #[test]
+    fn hash_nested() {
+        use super::List;
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn hash_of<T: Hash>(value: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        // [[1], [2]] and [[1, 2]] hold the same elements in the same order.
+        let split: List<List<i32>> = vec![(1..2).collect(), (2..3).collect()].into_iter().collect();
+        let joined: List<List<i32>> = vec![(1..3).collect()].into_iter().collect();
+        assert_ne!(hash_of(&split), hash_of(&joined));
+    }
+}
@@ -4,6 +4,10 @@ use std::cmp::Ordering;
 use std::fmt::{self, Debug};
 use std::hash::{Hash,Hasher};
 
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod sorted;
+
 pub struct LinkedList<T> {
     front: Link<T>,
     back: Link<T>,
@@ -176,7 +180,7 @@ impl<T> LinkedList<T> {
         self.len
     }
 
-    pub fn iter(&self) -> Iter<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
         Iter {
             front: self.front,
             back: self.back,
@@ -185,7 +189,7 @@ impl<T> LinkedList<T> {
         }
     }
 
-    pub fn iter_mut(&mut self) -> IterMut<T> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
         IterMut { 
             front: self.front,
             back: self.back,
@@ -203,13 +207,128 @@ impl<T> LinkedList<T> {
         while self.pop_front().is_some() {}
     }
 
-    pub fn cursor_mut(&mut self) -> CursorMut<T> {
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
         CursorMut {
             list: self,
             cur: None,
             index: None,
         }
     }
+
+    pub fn append(&mut self, other: &mut Self) {
+        // Moves all the nodes of `other` at the back of `self` in O(1).
+        // `other` is left empty.
+        if let Some(back) = self.back {
+            if let Some(other_front) = other.front.take() {
+                unsafe {
+                    (*back.as_ptr()).back = Some(other_front);
+                    (*other_front.as_ptr()).front = Some(back);
+                }
+                self.back = other.back.take();
+                self.len += std::mem::replace(&mut other.len, 0);
+            }
+        } else {
+            // We're empty, just become other.
+            std::mem::swap(self, other);
+        }
+    }
+
+    pub fn split_off(&mut self, at: usize) -> LinkedList<T> {
+        // Splits the list in two at the given index. Returns everything after
+        // the given index, including the index.
+        //
+        // Before (at = 2):
+        //  list.front -> A <-> B <-> C <-> D <- list.back
+        // After:
+        //  list.front -> A <-> B <- list.back
+        // And the function should return:
+        //  list.front -> C <-> D <- list.back
+        assert!(at <= self.len, "Cannot split off at a nonexistent index");
+        if at == 0 {
+            return std::mem::take(self);
+        } else if at == self.len {
+            return LinkedList::new();
+        }
+
+        unsafe {
+            // 0 < at < len so there is a node before the split point.
+            let split = node_at(self.front, self.back, self.len, at);
+            let prev = (*split.as_ptr()).front.take().unwrap();
+            (*prev.as_ptr()).back = None;
+
+            let output = LinkedList {
+                front: Some(split),
+                back: self.back,
+                len: self.len - at,
+                _boo: PhantomData,
+            };
+            self.back = Some(prev);
+            self.len = at;
+            output
+        }
+    }
+
+    pub fn rotate_left(&mut self, n: usize) {
+        // Moves the first `n` elements to the back, without touching a single
+        // element: we close the list into a ring and reopen it elsewhere.
+        //
+        // Before (n = 1):
+        //  list.front -> A <-> B <-> C <-> D <- list.back
+        // After:
+        //  list.front -> B <-> C <-> D <-> A <- list.back
+        assert!(n <= self.len, "Cannot rotate by more than the length");
+        if n == 0 || n == self.len {
+            return;
+        }
+
+        unsafe {
+            // 0 < n < len so we have at least two nodes.
+            let front = self.front.unwrap();
+            let back = self.back.unwrap();
+            // Finding the new front walks from the closer end, so this is
+            // O(min(n, len - n)).
+            let new_front = node_at(self.front, self.back, self.len, n);
+            let new_back = (*new_front.as_ptr()).front.unwrap();
+
+            (*back.as_ptr()).back = Some(front);
+            (*front.as_ptr()).front = Some(back);
+            (*new_back.as_ptr()).back = None;
+            (*new_front.as_ptr()).front = None;
+
+            self.front = Some(new_front);
+            self.back = Some(new_back);
+        }
+    }
+
+    pub fn rotate_right(&mut self, n: usize) {
+        // Moves the last `n` elements to the front.
+        assert!(n <= self.len, "Cannot rotate by more than the length");
+        self.rotate_left(self.len - n);
+    }
+}
+
+/// Walks to the node at `index` among the `len` nodes chained from `front` to
+/// `back`, starting from whichever end is closer.
+///
+/// # Safety
+///
+/// `front` and `back` must be the ends of a chain of exactly `len` live nodes
+/// and `index` must be smaller than `len`.
+unsafe fn node_at<T>(front: Link<T>, back: Link<T>, len: usize, index: usize) -> NonNull<Node<T>> {
+    debug_assert!(index < len);
+    if index < len / 2 {
+        let mut node = front.unwrap();
+        for _ in 0..index {
+            node = (*node.as_ptr()).back.unwrap();
+        }
+        node
+    } else {
+        let mut node = back.unwrap();
+        for _ in index + 1..len {
+            node = (*node.as_ptr()).front.unwrap();
+        }
+        node
+    }
 }
 
 impl<T> Default for LinkedList<T> {
@@ -469,6 +588,23 @@ impl<'a, T> CursorMut<'a, T> {
         }
     }
 
+    pub fn move_next_cyclic(&mut self) {
+        // Like move_next, but treats the list as a ring: from the back we
+        // wrap straight around to the front instead of stopping on the ghost.
+        self.move_next();
+        if self.cur.is_none() {
+            self.move_next();
+        }
+    }
+
+    pub fn move_prev_cyclic(&mut self) {
+        // Like move_prev, but from the front we wrap around to the back.
+        self.move_prev();
+        if self.cur.is_none() {
+            self.move_prev();
+        }
+    }
+
     pub fn split_before(&mut self) -> LinkedList<T> {
         // We want to perform the following operations
         //
@@ -523,7 +659,7 @@ impl<'a, T> CursorMut<'a, T> {
                 }
             }
         } else {
-            std::mem::replace(self.list, LinkedList::new())
+            std::mem::take(self.list)
         }
     }
 
@@ -581,7 +717,7 @@ impl<'a, T> CursorMut<'a, T> {
                 }
             }
         } else {
-            std::mem::replace(self.list, LinkedList::new())
+            std::mem::take(self.list)
         }
     }
 
@@ -812,7 +948,10 @@ pub fn assert_properties() {
     fn iter_mut_invariant() {}
 }
 
+// These tests are ported from std's, which spell things out on purpose:
+// `rev().next()` exercises `Rev`, and NaN must compare false every way.
 #[cfg(test)]
+#[allow(clippy::manual_next_back, clippy::useless_vec, clippy::zero_divided_by_zero, clippy::neg_cmp_op_on_partial_ord)]
 mod test {
     use super::LinkedList;
 
@@ -1194,6 +1333,115 @@ mod test {
         assert_eq!(m.iter().cloned().collect::<Vec<_>>(), &[200, 201, 202, 203, 1, 100, 101]);
     }
 
+    #[test]
+    fn test_append_split_off() {
+        let mut m = list_from(&[1, 2, 3]);
+        let mut n = list_from(&[4, 5]);
+        m.append(&mut n);
+        check_links(&m);
+        assert_eq!(m.len(), 5);
+        assert!(n.is_empty());
+        assert_eq!(m.iter().cloned().collect::<Vec<_>>(), &[1, 2, 3, 4, 5]);
+
+        n.append(&mut m);
+        assert!(m.is_empty());
+        assert_eq!(n.len(), 5);
+
+        for at in 0..=5 {
+            let mut m = n.clone();
+            let tail = m.split_off(at);
+            check_links(&m);
+            check_links(&tail);
+            assert_eq!(m.len(), at);
+            assert_eq!(tail.len(), 5 - at);
+            assert_eq!(m.into_iter().chain(tail).collect::<Vec<_>>(), &[1, 2, 3, 4, 5]);
+        }
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut m = generate_test();
+        m.rotate_left(0);
+        m.rotate_left(7);
+        assert_eq!(m, generate_test());
+
+        m.rotate_left(2);
+        check_links(&m);
+        assert_eq!(m.iter().cloned().collect::<Vec<_>>(), &[2, 3, 4, 5, 6, 0, 1]);
+        assert_eq!(m.front(), Some(&2));
+        assert_eq!(m.back(), Some(&1));
+
+        m.rotate_left(5);
+        check_links(&m);
+        assert_eq!(m, generate_test());
+
+        m.rotate_right(1);
+        check_links(&m);
+        assert_eq!(m.iter().cloned().collect::<Vec<_>>(), &[6, 0, 1, 2, 3, 4, 5]);
+        m.rotate_right(6);
+        check_links(&m);
+        assert_eq!(m, generate_test());
+
+        // Every rotation amount against the pop/push equivalent.
+        for n in 0..=7 {
+            let mut rotated = generate_test();
+            rotated.rotate_left(n);
+            let mut expected = generate_test();
+            for _ in 0..n {
+                let x = expected.pop_front().unwrap();
+                expected.push_back(x);
+            }
+            check_links(&rotated);
+            assert_eq!(rotated, expected);
+            assert_eq!(rotated.len(), 7);
+        }
+
+        let mut empty: LinkedList<i32> = LinkedList::new();
+        empty.rotate_left(0);
+        empty.rotate_right(0);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rotate_too_far() {
+        generate_test().rotate_right(8);
+    }
+
+    #[test]
+    fn test_cursor_cyclic() {
+        let mut m: LinkedList<u32> = LinkedList::new();
+        m.extend([1, 2, 3]);
+        let mut cursor = m.cursor_mut();
+        let mut seen = Vec::new();
+        for _ in 0..7 {
+            cursor.move_next_cyclic();
+            seen.push(*cursor.current().unwrap());
+        }
+        assert_eq!(seen, &[1, 2, 3, 1, 2, 3, 1]);
+        assert_eq!(cursor.index(), Some(0));
+
+        seen.clear();
+        for _ in 0..4 {
+            cursor.move_prev_cyclic();
+            seen.push(*cursor.current().unwrap());
+        }
+        assert_eq!(seen, &[3, 2, 1, 3]);
+        assert_eq!(cursor.index(), Some(2));
+
+        // Round robin: take the current one out and keep going.
+        assert_eq!(cursor.remove_current(), Some(3));
+        cursor.move_next_cyclic();
+        assert_eq!(cursor.current(), Some(&mut 1));
+
+        let mut empty: LinkedList<u32> = LinkedList::new();
+        let mut cursor = empty.cursor_mut();
+        cursor.move_next_cyclic();
+        cursor.move_prev_cyclic();
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.index(), None);
+    }
+
     fn check_links<T: Eq + std::fmt::Debug>(list: &LinkedList<T>) {
         let from_front: Vec<_> = list.iter().collect();
         let from_back: Vec<_> = list.iter().rev().collect();
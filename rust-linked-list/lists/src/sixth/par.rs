@@ -0,0 +1,323 @@
+//! Rayon parallel iterators for `LinkedList`, enabled by the `rayon` feature.
+//!
+//! A linked list cannot be split in O(1) like a slice, but since we know our
+//! `len` and have pointers to both ends we can still walk to any split point
+//! from the closer end. Rayon only splits a handful of times (roughly once per
+//! worker), so the walks are cheap compared to the work done on each half.
+
+use std::marker::PhantomData;
+
+use rayon::iter::plumbing::{bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer};
+use rayon::iter::{
+    FromParallelIterator, IndexedParallelIterator, IntoParallelIterator, ParallelExtend,
+    ParallelIterator,
+};
+
+use super::{node_at, IntoIter, Iter, IterMut, Link, LinkedList};
+
+pub struct IntoParIter<T> {
+    list: LinkedList<T>,
+}
+
+pub struct ParIter<'a, T> {
+    iter: Iter<'a, T>,
+}
+
+pub struct ParIterMut<'a, T> {
+    iter: IterMut<'a, T>,
+}
+
+/// Splits the `len` nodes chained from `front` to `back` before `index`.
+/// Returns the back of the left part and the front of the right part.
+///
+/// # Safety
+///
+/// `front` and `back` must be the ends of a chain of exactly `len` live nodes
+/// and `index` must be at most `len`.
+unsafe fn split_links<T>(front: Link<T>, back: Link<T>, len: usize, index: usize) -> (Link<T>, Link<T>) {
+    if index == 0 {
+        (None, front)
+    } else if index == len {
+        (back, None)
+    } else {
+        let split = node_at(front, back, len, index);
+        ((*split.as_ptr()).front, Some(split))
+    }
+}
+
+impl<T: Send> IntoParallelIterator for LinkedList<T> {
+    type Iter = IntoParIter<T>;
+    type Item = T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        IntoParIter { list: self }
+    }
+}
+
+impl<'a, T: Sync> IntoParallelIterator for &'a LinkedList<T> {
+    type Iter = ParIter<'a, T>;
+    type Item = &'a T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIter { iter: self.iter() }
+    }
+}
+
+impl<'a, T: Send> IntoParallelIterator for &'a mut LinkedList<T> {
+    type Iter = ParIterMut<'a, T>;
+    type Item = &'a mut T;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIterMut { iter: self.iter_mut() }
+    }
+}
+
+impl<T: Send> ParallelIterator for IntoParIter<T> {
+    type Item = T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.list.len)
+    }
+}
+
+impl<T: Send> IndexedParallelIterator for IntoParIter<T> {
+    fn drive<C: Consumer<Self::Item>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn len(&self) -> usize {
+        self.list.len
+    }
+
+    fn with_producer<CB: ProducerCallback<Self::Item>>(self, callback: CB) -> CB::Output {
+        callback.callback(ListProducer { list: self.list })
+    }
+}
+
+impl<'a, T: Sync> ParallelIterator for ParIter<'a, T> {
+    type Item = &'a T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.iter.len)
+    }
+}
+
+impl<'a, T: Sync> IndexedParallelIterator for ParIter<'a, T> {
+    fn drive<C: Consumer<Self::Item>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn len(&self) -> usize {
+        self.iter.len
+    }
+
+    fn with_producer<CB: ProducerCallback<Self::Item>>(self, callback: CB) -> CB::Output {
+        callback.callback(IterProducer { iter: self.iter })
+    }
+}
+
+impl<'a, T: Send> ParallelIterator for ParIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.iter.len)
+    }
+}
+
+impl<'a, T: Send> IndexedParallelIterator for ParIterMut<'a, T> {
+    fn drive<C: Consumer<Self::Item>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn len(&self) -> usize {
+        self.iter.len
+    }
+
+    fn with_producer<CB: ProducerCallback<Self::Item>>(self, callback: CB) -> CB::Output {
+        callback.callback(IterMutProducer { iter: self.iter })
+    }
+}
+
+struct ListProducer<T> {
+    list: LinkedList<T>,
+}
+
+struct IterProducer<'a, T> {
+    iter: Iter<'a, T>,
+}
+
+struct IterMutProducer<'a, T> {
+    iter: IterMut<'a, T>,
+}
+
+impl<T: Send> Producer for ListProducer<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter()
+    }
+
+    fn split_at(mut self, index: usize) -> (Self, Self) {
+        let right = self.list.split_off(index);
+        (self, ListProducer { list: right })
+    }
+}
+
+impl<'a, T: Sync> Producer for IterProducer<'a, T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let Iter { front, back, len, .. } = self.iter;
+        // The iterator covers a live sub-chain of the list it borrows.
+        let (left_back, right_front) = unsafe { split_links(front, back, len, index) };
+        let left = Iter { front, back: left_back, len: index, _boo: PhantomData };
+        let right = Iter { front: right_front, back, len: len - index, _boo: PhantomData };
+        (IterProducer { iter: left }, IterProducer { iter: right })
+    }
+}
+
+impl<'a, T: Send> Producer for IterMutProducer<'a, T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let IterMut { front, back, len, .. } = self.iter;
+        // The halves cover disjoint nodes, so handing out both is fine.
+        let (left_back, right_front) = unsafe { split_links(front, back, len, index) };
+        let left = IterMut { front, back: left_back, len: index, _boo: PhantomData };
+        let right = IterMut { front: right_front, back, len: len - index, _boo: PhantomData };
+        (IterMutProducer { iter: left }, IterMutProducer { iter: right })
+    }
+}
+
+impl<T: Send> ParallelExtend<T> for LinkedList<T> {
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        // Every worker builds its own list, which we then stitch together in
+        // order with O(1) appends.
+        let mut list = par_iter
+            .into_par_iter()
+            .fold(LinkedList::new, |mut list, elem| {
+                list.push_back(elem);
+                list
+            })
+            .reduce(LinkedList::new, |mut left, mut right| {
+                left.append(&mut right);
+                left
+            });
+        self.append(&mut list);
+    }
+}
+
+impl<T: Send> FromParallelIterator<T> for LinkedList<T> {
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        let mut list = LinkedList::new();
+        list.par_extend(par_iter);
+        list
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rayon::iter::plumbing::Producer;
+    use rayon::prelude::*;
+
+    use super::super::LinkedList;
+    use super::{IterMutProducer, IterProducer, ListProducer};
+
+    fn generate_test(len: i32) -> LinkedList<i32> {
+        (0..len).collect()
+    }
+
+    #[test]
+    fn test_par_iter() {
+        let list = generate_test(10_000);
+        let doubled: Vec<i32> = list.par_iter().map(|x| x * 2).collect();
+        assert_eq!(doubled, (0..10_000).map(|x| x * 2).collect::<Vec<_>>());
+        assert_eq!(list.par_iter().sum::<i32>(), (0..10_000).sum());
+
+        let empty: LinkedList<i32> = LinkedList::new();
+        assert_eq!(empty.par_iter().count(), 0);
+    }
+
+    #[test]
+    fn test_par_iter_mut() {
+        let mut list = generate_test(10_000);
+        list.par_iter_mut().for_each(|x| *x += 1);
+        assert!(list.iter().copied().eq(1..10_001));
+    }
+
+    #[test]
+    fn test_into_par_iter() {
+        let list: LinkedList<String> = (0..1_000).map(|x| x.to_string()).collect();
+        let lens: Vec<usize> = list.into_par_iter().map(|s| s.len()).collect();
+        assert_eq!(lens.len(), 1_000);
+        assert_eq!(lens[999], 3);
+    }
+
+    #[test]
+    fn test_split_every_index() {
+        // Exercise the producers directly on every split point, from both ends.
+        let mut list = generate_test(7);
+        for at in 0..=7 {
+            let (left, right) = IterProducer { iter: list.iter() }.split_at(at);
+            assert!(left.into_iter().copied().eq(0..at as i32));
+            assert!(right.into_iter().rev().copied().eq((at as i32..7).rev()));
+
+            let (left, right) = IterMutProducer { iter: list.iter_mut() }.split_at(at);
+            assert!(left.into_iter().rev().map(|x| *x).eq((0..at as i32).rev()));
+            assert!(right.into_iter().map(|x| *x).eq(at as i32..7));
+
+            let (left, right) = ListProducer { list: list.clone() }.split_at(at);
+            assert!(left.into_iter().eq(0..at as i32));
+            assert!(right.into_iter().eq(at as i32..7));
+        }
+    }
+
+    #[test]
+    fn test_par_extend() {
+        let mut list = generate_test(3);
+        list.par_extend((3..10_000).into_par_iter());
+        assert!(list.iter().copied().eq(0..10_000));
+        assert!(list.iter().rev().copied().eq((0..10_000).rev()));
+
+        let collected: LinkedList<i32> = (0..10_000).into_par_iter().collect();
+        assert_eq!(collected, generate_test(10_000));
+    }
+}
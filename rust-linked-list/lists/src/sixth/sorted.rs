@@ -0,0 +1,454 @@
+//! A `LinkedList` that keeps its elements sorted on insert.
+//!
+//! Popping from either end gives a double-ended priority queue: `pop_min` and
+//! `pop_max` are O(1). Equal elements keep their insertion order, so popping
+//! the minimum repeatedly behaves like a stable (FIFO) priority queue.
+
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::ptr::NonNull;
+
+use super::{IntoIter, Iter, Link, LinkedList, Node};
+
+pub struct SortedList<T, C = fn(&T, &T) -> Ordering> {
+    list: LinkedList<T>,
+    cmp: C,
+}
+
+impl<T: Ord> SortedList<T> {
+    pub fn new() -> Self {
+        SortedList::by(T::cmp)
+    }
+}
+
+impl<T> SortedList<T> {
+    /// Sorts the elements by the key extracted with `key`, like
+    /// `slice::sort_by_key`.
+    pub fn by_key<K, F>(key: F) -> SortedList<T, impl Fn(&T, &T) -> Ordering>
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        SortedList::by(move |a: &T, b: &T| key(a).cmp(&key(b)))
+    }
+}
+
+impl<T, C: Fn(&T, &T) -> Ordering> SortedList<T, C> {
+    /// Sorts the elements with the given comparator, like `slice::sort_by`.
+    pub fn by(cmp: C) -> Self {
+        SortedList {
+            list: LinkedList::new(),
+            cmp,
+        }
+    }
+
+    pub fn insert(&mut self, elem: T) {
+        match unsafe { self.insert_point(&elem) } {
+            None => self.list.push_back(elem),
+            Some(next) if Some(next) == self.list.front => self.list.push_front(elem),
+            Some(next) => unsafe {
+                // We are not at the front so there is a node before us.
+                let prev = (*next.as_ptr()).front.unwrap();
+                let new = NonNull::new_unchecked(Box::into_raw(Box::new(Node {
+                    front: Some(prev),
+                    back: Some(next),
+                    elem,
+                })));
+                (*prev.as_ptr()).back = Some(new);
+                (*next.as_ptr()).front = Some(new);
+                self.list.len += 1;
+            },
+        }
+    }
+
+    /// Finds the node `elem` must be inserted before, `None` meaning the back.
+    ///
+    /// That's the first node strictly greater than `elem`, so that equal
+    /// elements stay in insertion order. We look for it from both ends at
+    /// once, so we only walk as far as the closer end is from the spot.
+    unsafe fn insert_point(&self, elem: &T) -> Link<T> {
+        let mut forward = self.list.front;
+        let mut backward = self.list.back;
+        loop {
+            // From the front, stop on the first node greater than elem.
+            match forward {
+                Some(node) if (self.cmp)(&(*node.as_ptr()).elem, elem) == Ordering::Greater => {
+                    return Some(node);
+                }
+                Some(node) => forward = (*node.as_ptr()).back,
+                None => return None,
+            }
+            // From the back, stop on the last node not greater than elem.
+            match backward {
+                Some(node) if (self.cmp)(&(*node.as_ptr()).elem, elem) != Ordering::Greater => {
+                    return (*node.as_ptr()).back;
+                }
+                Some(node) => backward = (*node.as_ptr()).front,
+                None => return self.list.front,
+            }
+        }
+    }
+
+    /// Finds the first node equal to `elem`, giving up as soon as we walk
+    /// past where it would be.
+    fn find(&self, elem: &T) -> Link<T> {
+        let mut cur = self.list.front;
+        while let Some(node) = cur {
+            unsafe {
+                match (self.cmp)(&(*node.as_ptr()).elem, elem) {
+                    Ordering::Less => cur = (*node.as_ptr()).back,
+                    Ordering::Equal => return Some(node),
+                    Ordering::Greater => return None,
+                }
+            }
+        }
+        None
+    }
+
+    pub fn contains(&self, elem: &T) -> bool {
+        self.find(elem).is_some()
+    }
+
+    /// Removes the first element equal to `elem` and returns it.
+    pub fn remove(&mut self, elem: &T) -> Option<T> {
+        let node = self.find(elem)?;
+        unsafe {
+            let boxed_node = Box::from_raw(node.as_ptr());
+            match boxed_node.front {
+                Some(prev) => (*prev.as_ptr()).back = boxed_node.back,
+                None => self.list.front = boxed_node.back,
+            }
+            match boxed_node.back {
+                Some(next) => (*next.as_ptr()).front = boxed_node.front,
+                None => self.list.back = boxed_node.front,
+            }
+            self.list.len -= 1;
+            Some(boxed_node.elem)
+        }
+    }
+
+    /// Merges another sorted list into this one. On ties, elements already
+    /// in `self` come first.
+    ///
+    /// `other` may be sorted by a different comparator: two `fn` pointers
+    /// have the same type. So its elements are sorted again by ours first,
+    /// which only takes linear time when they already are in order.
+    pub fn merge(&mut self, other: Self) {
+        let mut right: Vec<T> = other.list.into_iter().collect();
+        right.sort_by(&self.cmp);
+        let mut right = right.into_iter().peekable();
+
+        let mut left = std::mem::take(&mut self.list);
+        let mut merged = LinkedList::new();
+        while let (Some(a), Some(b)) = (left.front(), right.peek()) {
+            if (self.cmp)(b, a) == Ordering::Less {
+                merged.extend(right.next());
+            } else {
+                merged.extend(left.pop_front());
+            }
+        }
+        // At most one of them still has elements, all bigger than the rest.
+        merged.append(&mut left);
+        merged.extend(right);
+        self.list = merged;
+    }
+
+    /// Iterates over the elements within `range`, in order.
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> Iter<'_, T> {
+        let after_start = |elem: &T| match range.start_bound() {
+            Bound::Included(start) => (self.cmp)(elem, start) != Ordering::Less,
+            Bound::Excluded(start) => (self.cmp)(elem, start) == Ordering::Greater,
+            Bound::Unbounded => true,
+        };
+        let before_end = |elem: &T| match range.end_bound() {
+            Bound::Included(end) => (self.cmp)(elem, end) != Ordering::Greater,
+            Bound::Excluded(end) => (self.cmp)(elem, end) == Ordering::Less,
+            Bound::Unbounded => true,
+        };
+
+        let mut front = self.list.front;
+        let mut back = None;
+        let mut len = 0;
+        unsafe {
+            while let Some(node) = front {
+                if after_start(&(*node.as_ptr()).elem) {
+                    break;
+                }
+                front = (*node.as_ptr()).back;
+            }
+            let mut cur = front;
+            while let Some(node) = cur {
+                if !before_end(&(*node.as_ptr()).elem) {
+                    break;
+                }
+                back = Some(node);
+                len += 1;
+                cur = (*node.as_ptr()).back;
+            }
+        }
+
+        Iter {
+            front,
+            back,
+            len,
+            _boo: PhantomData,
+        }
+    }
+}
+
+impl<T, C> SortedList<T, C> {
+    pub fn peek_min(&self) -> Option<&T> {
+        self.list.front()
+    }
+
+    pub fn peek_max(&self) -> Option<&T> {
+        self.list.back()
+    }
+
+    pub fn pop_min(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+
+    pub fn pop_max(&mut self) -> Option<T> {
+        self.list.pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.list.iter()
+    }
+
+    pub fn into_list(self) -> LinkedList<T> {
+        self.list
+    }
+}
+
+impl<T: Ord> Default for SortedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, C: Clone> Clone for SortedList<T, C> {
+    fn clone(&self) -> Self {
+        SortedList {
+            list: self.list.clone(),
+            cmp: self.cmp.clone(),
+        }
+    }
+}
+
+impl<T: Debug, C> Debug for SortedList<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, C: Fn(&T, &T) -> Ordering> Extend<T> for SortedList<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SortedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T, C> IntoIterator for SortedList<T, C> {
+    type IntoIter = IntoIter<T>;
+    type Item = T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter()
+    }
+}
+
+impl<'a, T, C> IntoIterator for &'a SortedList<T, C> {
+    type IntoIter = Iter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+    use std::ops::Bound;
+
+    use super::SortedList;
+
+    fn check_sorted<T: std::fmt::Debug + PartialEq, C: Fn(&T, &T) -> Ordering>(list: &SortedList<T, C>) {
+        let from_front: Vec<_> = list.iter().collect();
+        let mut from_back: Vec<_> = list.iter().rev().collect();
+        from_back.reverse();
+        assert_eq!(from_front, from_back);
+        assert_eq!(from_front.len(), list.len());
+        assert!(from_front.windows(2).all(|w| (list.cmp)(w[0], w[1]) != Ordering::Greater), "{:?}", from_front);
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut list = SortedList::new();
+        check_sorted(&list);
+        for x in [5, 1, 9, 3, 7, 3, 0, 10, 5, 6] {
+            list.insert(x);
+            check_sorted(&list);
+        }
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[0, 1, 3, 3, 5, 5, 6, 7, 9, 10]);
+
+        // Scrambled insertions so both ends of the search get to win.
+        let mut list = SortedList::new();
+        for i in 0..200u32 {
+            list.insert(i.wrapping_mul(7919) % 101);
+            check_sorted(&list);
+        }
+        assert_eq!(list.len(), 200);
+    }
+
+    #[test]
+    fn test_remove_contains() {
+        let mut list: SortedList<i32> = [4, 2, 8, 6, 2].into_iter().collect();
+        assert!(list.contains(&2));
+        assert!(!list.contains(&5));
+        assert!(!list.contains(&100));
+
+        assert_eq!(list.remove(&2), Some(2));
+        check_sorted(&list);
+        assert!(list.contains(&2));
+        assert_eq!(list.remove(&2), Some(2));
+        check_sorted(&list);
+        assert!(!list.contains(&2));
+        assert_eq!(list.remove(&2), None);
+
+        assert_eq!(list.remove(&8), Some(8));
+        check_sorted(&list);
+        assert_eq!(list.remove(&4), Some(4));
+        check_sorted(&list);
+        assert_eq!(list.remove(&6), Some(6));
+        check_sorted(&list);
+        assert!(list.is_empty());
+        assert_eq!(list.remove(&6), None);
+
+        list.insert(1);
+        check_sorted(&list);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[1]);
+    }
+
+    #[test]
+    fn test_priority_queue() {
+        let mut list: SortedList<i32> = [3, 1, 4, 1, 5, 9, 2, 6].into_iter().collect();
+        assert_eq!(list.peek_min(), Some(&1));
+        assert_eq!(list.peek_max(), Some(&9));
+        assert_eq!(list.pop_min(), Some(1));
+        check_sorted(&list);
+        assert_eq!(list.pop_max(), Some(9));
+        check_sorted(&list);
+        list.insert(0);
+        list.insert(10);
+        check_sorted(&list);
+        assert_eq!(list.pop_min(), Some(0));
+        assert_eq!(list.pop_max(), Some(10));
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut left: SortedList<i32> = [1, 4, 4, 7].into_iter().collect();
+        let right: SortedList<i32> = [0, 4, 8, 9].into_iter().collect();
+        left.merge(right);
+        check_sorted(&left);
+        assert_eq!(left.iter().copied().collect::<Vec<_>>(), &[0, 1, 4, 4, 4, 7, 8, 9]);
+
+        left.merge(SortedList::new());
+        check_sorted(&left);
+        assert_eq!(left.len(), 8);
+
+        let mut empty = SortedList::new();
+        empty.merge(left);
+        check_sorted(&empty);
+        assert_eq!(empty.len(), 8);
+
+        // Same type, other order: `other` is sorted by our comparator first.
+        let descending = |a: &i32, b: &i32| b.cmp(a);
+        let mut left: SortedList<i32> = [1, 4, 7].into_iter().collect();
+        let mut right: SortedList<i32> = SortedList::by(descending);
+        right.extend([0, 5, 9, 4]);
+        check_sorted(&right);
+        left.merge(right);
+        check_sorted(&left);
+        assert_eq!(left.iter().copied().collect::<Vec<_>>(), &[0, 1, 4, 4, 5, 7, 9]);
+    }
+
+    #[test]
+    fn test_range() {
+        let list: SortedList<i32> = (0..10).rev().collect();
+        let collect = |it: super::Iter<'_, i32>| it.copied().collect::<Vec<_>>();
+        assert_eq!(collect(list.range(3..6)), &[3, 4, 5]);
+        assert_eq!(collect(list.range(3..=6)), &[3, 4, 5, 6]);
+        assert_eq!(collect(list.range(..2)), &[0, 1]);
+        assert_eq!(collect(list.range(8..)), &[8, 9]);
+        assert_eq!(collect(list.range(..)).len(), 10);
+        assert_eq!(collect(list.range(20..)), &[]);
+        assert_eq!(collect(list.range(5..5)), &[]);
+        assert_eq!(list.range(2..8).next_back(), Some(&7));
+        assert_eq!(list.range(2..8).len(), 6);
+    }
+
+    #[test]
+    fn test_by_key() {
+        let mut list = SortedList::by_key(|&(priority, _): &(u32, &str)| priority);
+        for elem in [(2, "b"), (1, "a"), (2, "c"), (0, "z"), (2, "d")] {
+            list.insert(elem);
+            check_sorted(&list);
+        }
+
+        // Ties keep their insertion order.
+        assert_eq!(
+            list.iter().map(|&(_, name)| name).collect::<Vec<_>>(),
+            &["z", "a", "b", "c", "d"]
+        );
+        assert!(list.contains(&(2, "anything")));
+        assert_eq!(list.remove(&(2, "anything")), Some((2, "b")));
+        check_sorted(&list);
+        assert_eq!(list.pop_max(), Some((2, "d")));
+        check_sorted(&list);
+        assert_eq!(list.pop_min(), Some((0, "z")));
+        check_sorted(&list);
+    }
+
+    #[test]
+    fn test_by() {
+        // Descending order, so ranges are given from the biggest bound.
+        let mut list = SortedList::by(|a: &i32, b: &i32| b.cmp(a));
+        list.extend([1, 5, 3, 0]);
+        check_sorted(&list);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[5, 3, 1, 0]);
+        assert_eq!(list.range((Bound::Included(4), Bound::Included(1))).copied().collect::<Vec<_>>(), &[3, 1]);
+        assert_eq!(list.pop_min(), Some(5));
+        check_sorted(&list);
+        assert_eq!(list.remove(&1), Some(1));
+        check_sorted(&list);
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), &[3, 0]);
+    }
+}
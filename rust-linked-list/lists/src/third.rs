@@ -1,3 +1,4 @@
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
 pub struct List<T> {
@@ -49,11 +50,44 @@ impl<T> List<T> {
         List { head: self.head.as_ref().and_then(|node| node.next.clone()) }
     }
 
-    pub fn iter(&self) -> Iter<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
         Iter { next: self.head.as_deref() }
     }
 }
 
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Cloning a persistent list only bumps the head's refcount: every node is
+// shared, nothing gets copied.
+impl<T> Clone for List<T> {
+    fn clone(&self) -> Self {
+        List { head: self.head.clone() }
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+impl<T: Hash> Hash for List<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Counted first: in a list of lists the inner lists end nowhere
+        // else, so [[1, 2], [3]] would hash like [[1], [2, 3]].
+        self.iter().count().hash(state);
+        for elem in self.iter() {
+            elem.hash(state);
+        }
+    }
+}
+
 impl<T> Drop for List<T> {
     fn drop(&mut self) {
         let mut head = self.head.take();
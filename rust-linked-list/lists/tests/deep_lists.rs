@@ -0,0 +1,134 @@
+// Every list here is built, cloned, compared, hashed and dropped on a thread
+// with a tiny stack. Anything recursing once per node blows that stack after a
+// few thousand nodes, so getting through a million of them proves the code
+// paths are iterative.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::thread;
+
+use lists::{first, second, third};
+
+// Far past what recursion survives on SMALL_STACK, and small enough to run
+// alongside the rest of the test suite.
+const DEPTH: usize = 1_000_000;
+// Tens of millions of nodes, as real lists get. That's about a GiB and a
+// while in a debug build, so these only run with `cargo test -- --ignored`.
+const HUGE_DEPTH: usize = 10_000_000;
+const SMALL_STACK: usize = 64 * 1024;
+
+fn on_small_stack<F: FnOnce() + Send + 'static>(f: F) {
+    thread::Builder::new()
+        .stack_size(SMALL_STACK)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn deep_first_list() {
+    first_list(DEPTH);
+}
+
+#[test]
+#[ignore]
+fn huge_first_list() {
+    first_list(HUGE_DEPTH);
+}
+
+fn first_list(depth: usize) {
+    on_small_stack(move || {
+        let mut list = first::List::new();
+        for i in 0..depth {
+            list.push(i as i32);
+        }
+
+        let mut cloned = list.clone();
+        assert!(list == cloned);
+        assert_eq!(hash_of(&list), hash_of(&cloned));
+
+        assert_eq!(cloned.pop(), Some(depth as i32 - 1));
+        assert!(list != cloned);
+
+        drop(list);
+        drop(cloned);
+    });
+}
+
+#[test]
+fn deep_second_list() {
+    second_list(DEPTH);
+}
+
+#[test]
+#[ignore]
+fn huge_second_list() {
+    second_list(HUGE_DEPTH);
+}
+
+fn second_list(depth: usize) {
+    on_small_stack(move || {
+        let mut list: second::List<usize> = (0..depth).collect();
+        assert_eq!(list.len(), depth);
+
+        let mut cloned = list.clone();
+        assert_eq!(list, cloned);
+        assert_eq!(hash_of(&list), hash_of(&cloned));
+
+        cloned.reverse();
+        assert_eq!(cloned.peek(), Some(&0));
+        assert_ne!(list, cloned);
+
+        list.extend(0..depth);
+        assert_eq!(list.len(), 2 * depth);
+
+        let mut iter = cloned.into_iter();
+        assert_eq!(iter.next(), Some(0));
+        // Drops the half-consumed iterator, then the list.
+        drop(iter);
+        drop(list);
+    });
+}
+
+#[test]
+fn deep_third_list() {
+    third_list(DEPTH);
+}
+
+#[test]
+#[ignore]
+fn huge_third_list() {
+    third_list(HUGE_DEPTH);
+}
+
+fn third_list(depth: usize) {
+    on_small_stack(move || {
+        let mut list = third::List::new();
+        for i in 0..depth {
+            list = list.prepend(i);
+        }
+
+        let cloned = list.clone();
+        assert!(list == cloned);
+        assert_eq!(hash_of(&list), hash_of(&cloned));
+
+        // Two lists sharing everything but their head. Dropping one of them
+        // has to stop at the first shared node, dropping the last owner has
+        // to free the whole chain.
+        let tail = list.tail();
+        let forked = tail.prepend(depth + 1);
+        assert!(forked != list);
+        drop(list);
+        drop(tail);
+        assert_eq!(cloned.head(), Some(&(depth - 1)));
+        drop(cloned);
+        assert_eq!(forked.iter().count(), depth);
+        drop(forked);
+    });
+}